@@ -2,6 +2,21 @@
 
 set -e
 
+# Optional extras, e.g. curl -fsSL .../install.sh | bash -s -- --completions --man
+INSTALL_COMPLETIONS=false
+INSTALL_MAN=false
+for arg in "$@"; do
+    case "$arg" in
+        --completions) INSTALL_COMPLETIONS=true ;;
+        --man) INSTALL_MAN=true ;;
+        *)
+            echo "Error: Unknown option: $arg"
+            echo "Usage: install.sh [--completions] [--man]"
+            exit 1
+            ;;
+    esac
+done
+
 echo "Installing kit..."
 
 # Detect OS and architecture
@@ -83,6 +98,43 @@ if [[ ":$PATH:" != *":$INSTALL_DIR:"* ]]; then
     echo "  export PATH=\"\$PATH:$INSTALL_DIR\""
 fi
 
+# Install shell completions for the user's login shell
+if [ "$INSTALL_COMPLETIONS" = true ]; then
+    case "$(basename "$SHELL")" in
+        bash)
+            COMPLETIONS_DIR="${XDG_DATA_HOME:-$HOME/.local/share}/bash-completion/completions"
+            mkdir -p "$COMPLETIONS_DIR"
+            "$INSTALL_DIR/kit" completions bash > "$COMPLETIONS_DIR/kit"
+            echo "Installed bash completions to $COMPLETIONS_DIR/kit"
+            ;;
+        fish)
+            COMPLETIONS_DIR="${XDG_CONFIG_HOME:-$HOME/.config}/fish/completions"
+            mkdir -p "$COMPLETIONS_DIR"
+            "$INSTALL_DIR/kit" completions fish > "$COMPLETIONS_DIR/kit.fish"
+            echo "Installed fish completions to $COMPLETIONS_DIR/kit.fish"
+            ;;
+        zsh)
+            echo "To enable zsh completions, add this line to your ~/.zshrc:"
+            echo "  source <(COMPLETE=zsh kit)"
+            ;;
+        *)
+            echo "Warning: Don't know how to install completions for $SHELL"
+            echo "Run 'kit completions --help' to set them up manually."
+            ;;
+    esac
+fi
+
+# Install man pages next to the binary so man finds them through PATH
+if [ "$INSTALL_MAN" = true ]; then
+    MAN_DIR="$(dirname "$INSTALL_DIR")/share/man/man1"
+    if mkdir -p "$MAN_DIR" 2>/dev/null && [ -w "$MAN_DIR" ]; then
+        "$INSTALL_DIR/kit" man --dir "$MAN_DIR"
+        echo "Installed man pages to $MAN_DIR"
+    else
+        echo "Warning: Could not write man pages to $MAN_DIR"
+    fi
+fi
+
 echo ""
 echo "kit installed successfully!"
 echo ""
@@ -0,0 +1,393 @@
+//! On-disk cache for GitHub API responses.
+//!
+//! Entries live under `<cache dir>/kit/http`, keyed by a hash of the URL and
+//! the Authorization header so accounts never see each other's responses.
+//! GraphQL queries are all POSTed to one URL, so their body is hashed too.
+//! Within the TTL an entry is used without asking GitHub; after that it is
+//! revalidated with `If-None-Match`/`If-Modified-Since`, and the 304s GitHub
+//! answers with don't count against the rate limit.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use log::{debug, warn};
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{self, CacheSettings};
+use crate::http;
+use crate::utils;
+
+const HTTP_DIR: &str = "http";
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+static SETTINGS: OnceLock<CacheSettings> = OnceLock::new();
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored_at: u64,
+    body: String,
+}
+
+/// A response body that may have come from the cache. Only successful
+/// responses are ever cached.
+pub struct CachedResponse {
+    status: StatusCode,
+    body: String,
+}
+
+impl CachedResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn text(self) -> String {
+        self.body
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+#[derive(Serialize)]
+pub struct CacheStats {
+    pub path: String,
+    pub entries: usize,
+    pub bytes: u64,
+    /// Entries past the TTL, which are revalidated on next use.
+    pub expired: usize,
+}
+
+#[derive(Serialize)]
+pub struct ClearResult {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// kit's cache directory, also home to the shell completion caches.
+pub fn dir() -> Option<PathBuf> {
+    match DIR.get() {
+        Some(dir) => Some(dir.clone()),
+        None => Some(dirs::cache_dir()?.join("kit")),
+    }
+}
+
+#[cfg(test)]
+pub fn set_dir(path: PathBuf) {
+    let _ = DIR.set(path);
+}
+
+fn settings() -> &'static CacheSettings {
+    SETTINGS.get_or_init(|| {
+        config::load_cache_settings().unwrap_or_else(|e| {
+            warn!("Could not load cache settings, using defaults: {}", e);
+            CacheSettings::default()
+        })
+    })
+}
+
+pub trait SendCached {
+    /// Like `SendWithRetry::send_with_retry`, but GET requests are answered
+    /// from and stored in the on-disk cache.
+    fn send_cached(self) -> Result<CachedResponse>;
+
+    /// Like `send_cached`, for POST requests that only read, such as GraphQL
+    /// queries.
+    fn send_cached_query(self) -> Result<CachedResponse>;
+}
+
+impl SendCached for RequestBuilder {
+    fn send_cached(self) -> Result<CachedResponse> {
+        let (client, request) = self.build_split();
+        send(&client, request?, settings())
+    }
+
+    fn send_cached_query(self) -> Result<CachedResponse> {
+        let (client, request) = self.build_split();
+        send_as_get(&client, request?, settings())
+    }
+}
+
+fn send(client: &Client, request: Request, settings: &CacheSettings) -> Result<CachedResponse> {
+    if request.method() != Method::GET {
+        return uncached(http::execute_with_retry(client, request)?);
+    }
+    send_as_get(client, request, settings)
+}
+
+// Caches `request` whatever its method, so only for requests that read.
+fn send_as_get(client: &Client, mut request: Request, settings: &CacheSettings) -> Result<CachedResponse> {
+    let path = match dir() {
+        Some(dir) if settings.enabled => {
+            dir.join(HTTP_DIR).join(format!("{}.json", key(&request)))
+        }
+        _ => return uncached(http::execute_with_retry(client, request)?),
+    };
+
+    let cached = read_entry(&path);
+    if let Some(entry) = &cached {
+        if now().saturating_sub(entry.stored_at) < settings.ttl {
+            debug!("Using cached response for {}", entry.url);
+            return Ok(CachedResponse { status: StatusCode::OK, body: entry.body.clone() });
+        }
+        let validators = [(IF_NONE_MATCH, &entry.etag), (IF_MODIFIED_SINCE, &entry.last_modified)];
+        for (name, value) in validators {
+            if let Some(value) = value.as_deref().and_then(|value| HeaderValue::from_str(value).ok()) {
+                request.headers_mut().insert(name, value);
+            }
+        }
+    }
+
+    let url = request.url().to_string();
+    let response = http::execute_with_retry(client, request)?;
+
+    if response.status() == StatusCode::NOT_MODIFIED
+        && let Some(mut entry) = cached
+    {
+        debug!("{} not modified, refreshing cached response", url);
+        entry.stored_at = now();
+        write_entry(&path, &entry);
+        return Ok(CachedResponse { status: StatusCode::OK, body: entry.body });
+    }
+
+    let status = response.status();
+    let etag = header(&response, ETAG);
+    let last_modified = header(&response, LAST_MODIFIED);
+    let body = response.text()?;
+
+    if status == StatusCode::OK {
+        let entry = Entry { url, etag, last_modified, stored_at: now(), body };
+        write_entry(&path, &entry);
+        return Ok(CachedResponse { status, body: entry.body });
+    }
+    Ok(CachedResponse { status, body })
+}
+
+fn uncached(response: Response) -> Result<CachedResponse> {
+    Ok(CachedResponse { status: response.status(), body: response.text()? })
+}
+
+fn key(request: &Request) -> String {
+    let authorization = request
+        .headers()
+        .get(AUTHORIZATION)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+
+    let body = request.body().and_then(|body| body.as_bytes()).unwrap_or_default();
+
+    let mut material = format!("{} {}\n", request.method(), request.url()).into_bytes();
+    material.extend_from_slice(authorization);
+    material.push(b'\n');
+    material.extend_from_slice(body);
+    utils::sha256_hex(&material)
+}
+
+fn header(response: &Response, name: HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn read_entry(path: &Path) -> Option<Entry> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+// A cache that can't be written just means the next request goes to GitHub.
+fn write_entry(path: &Path, entry: &Entry) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), create_private_dir)
+        .map_err(anyhow::Error::from)
+        .and_then(|_| write_private(path, &serde_json::to_vec(entry)?));
+
+    if let Err(e) = result {
+        debug!("Could not write cache entry {}: {}", path.display(), e);
+    }
+}
+
+/// Entries hold private repositories' responses, so only the user may list
+/// the cache.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+// Written to a temporary file (readable by the user only) and renamed over
+// the entry, which also tightens entries written by older versions.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(contents)?;
+    file.persist(path)?;
+    Ok(())
+}
+
+pub fn stats() -> Result<CacheStats> {
+    let dir = dir().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    let ttl = settings().ttl;
+
+    let mut stats = CacheStats { path: dir.display().to_string(), entries: 0, bytes: 0, expired: 0 };
+    for (path, size) in files(&dir.join(HTTP_DIR)) {
+        stats.entries += 1;
+        stats.bytes += size;
+        if read_entry(&path).is_none_or(|entry| now().saturating_sub(entry.stored_at) >= ttl) {
+            stats.expired += 1;
+        }
+    }
+    Ok(stats)
+}
+
+/// Deletes the cached responses for `url` and anything below it, such as
+/// `/repos/{owner}/{repo}/branches` for the repository's URL. Best effort: a
+/// cache that can't be cleaned up only means stale results until the TTL.
+pub fn invalidate(url: &str) {
+    let Some(dir) = dir() else {
+        return;
+    };
+    for (path, _) in files(&dir.join(HTTP_DIR)) {
+        let covered = read_entry(&path).is_some_and(|entry| {
+            entry.url.strip_prefix(url).is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+        });
+        if covered && let Err(e) = fs::remove_file(&path) {
+            debug!("Could not remove cache entry {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Deletes everything under the cache directory, including the repository
+/// list used by shell completion.
+pub fn clear() -> Result<ClearResult> {
+    let dir = dir().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    let files = files(&dir);
+
+    let result = ClearResult {
+        path: dir.display().to_string(),
+        files: files.len(),
+        bytes: files.iter().map(|(_, size)| size).sum(),
+    };
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    Ok(result)
+}
+
+fn files(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => files.extend(self::files(&path)),
+            Ok(metadata) => files.push((path, metadata.len())),
+            Err(_) => continue,
+        }
+    }
+    files
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn expired_entries_are_revalidated_with_etag() {
+        let server = test_support::server();
+        server.route("GET", "/api/cache/etag", &["http/etag_ok", "http/not_modified"], None);
+        let url = format!("{}/api/cache/etag", server.base_url);
+        let client = http::get_client();
+        let request = || client.get(&url).header(AUTHORIZATION, "Bearer test-token").build().unwrap();
+
+        let always_revalidate = CacheSettings { enabled: true, ttl: 0 };
+        let first = send(client, request(), &always_revalidate).unwrap();
+        let second = send(client, request(), &always_revalidate).unwrap();
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(first.text(), second.text());
+
+        let requests = server.requests_to("/api/cache/etag");
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"6d82cbb050ddc7fa9cbb659014546e59\""));
+
+        let fresh = CacheSettings { enabled: true, ttl: 3600 };
+        send(client, request(), &fresh).unwrap();
+        assert_eq!(server.requests_to("/api/cache/etag").len(), 2);
+    }
+
+    #[test]
+    fn invalidate_drops_entries_below_a_url_only() {
+        let server = test_support::server();
+        server.route("GET", "/api/cache/repos/octocat/kit/branches", &["http/etag_ok", "http/etag_ok"], None);
+        server.route("GET", "/api/cache/repos/octocat/kit-cli", &["http/etag_ok"], None);
+        let client = http::get_client();
+        let fresh = CacheSettings { enabled: true, ttl: 3600 };
+        let get = |path: &str| {
+            let request = client.get(format!("{}{}", server.base_url, path)).build().unwrap();
+            send(client, request, &fresh).unwrap();
+        };
+
+        get("/api/cache/repos/octocat/kit/branches");
+        get("/api/cache/repos/octocat/kit-cli");
+        invalidate(&format!("{}/api/cache/repos/octocat/kit", server.base_url));
+        get("/api/cache/repos/octocat/kit/branches");
+        get("/api/cache/repos/octocat/kit-cli");
+
+        assert_eq!(server.requests_to("/api/cache/repos/octocat/kit/branches").len(), 2);
+        assert_eq!(server.requests_to("/api/cache/repos/octocat/kit-cli").len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entries_are_only_readable_by_the_user() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("kit").join(HTTP_DIR).join("entry.json");
+        let entry = Entry {
+            url: "https://api.github.com/user".to_string(),
+            etag: None,
+            last_modified: None,
+            stored_at: 0,
+            body: "{}".to_string(),
+        };
+        write_entry(&path, &entry);
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&root.path().join("kit")), 0o700);
+        assert_eq!(mode(&root.path().join("kit").join(HTTP_DIR)), 0o700);
+        assert_eq!(read_entry(&path).unwrap().body, "{}");
+    }
+}
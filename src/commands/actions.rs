@@ -0,0 +1,179 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use colored::*;
+use log::{info, warn};
+
+use crate::commands::github::{self, Job, WorkflowRun};
+use crate::output::{self, OutputFormat};
+use crate::utils::cancel;
+
+// Also how often a cancelled watch is noticed while waiting for the next poll.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const MAX_LINE_WIDTH: usize = 72;
+
+/// A one-character summary of a run, job or step.
+pub fn symbol(status: Option<&str>, conclusion: Option<&str>) -> ColoredString {
+    match (status, conclusion) {
+        (Some("completed"), Some("success")) => "✓".green(),
+        (Some("completed"), Some("failure" | "timed_out" | "startup_failure")) => "✗".red(),
+        (Some("completed"), Some("cancelled")) => "⊘".yellow(),
+        (Some("completed"), _) => "-".bright_black(),
+        (Some("in_progress"), _) => "●".yellow(),
+        _ => "○".bright_black(),
+    }
+}
+
+pub fn succeeded(conclusion: Option<&str>) -> bool {
+    matches!(conclusion, Some("success" | "skipped" | "neutral"))
+}
+
+/// `2026-10-19T08:12:44Z` as `2026-10-19 08:12`.
+pub fn short_time(timestamp: &str) -> String {
+    timestamp.replace('T', " ").chars().take(16).collect()
+}
+
+/// Follows a run until it completes, with a spinner line for every job still
+/// running and a line for each job as it finishes. Interrupting the watch
+/// with Ctrl-C is an error, since the run's outcome is still unknown.
+pub fn watch(token: &str, owner: &str, repo_name: &str, run_id: u64, interval: Duration) -> Result<WorkflowRun> {
+    let cancel_scope = cancel::scope()?;
+    let mut reported: HashSet<u64> = HashSet::new();
+    let mut started: HashMap<u64, Instant> = HashMap::new();
+    let mut board = JobBoard::new();
+
+    loop {
+        let run = github::get_workflow_run(token, owner, repo_name, run_id)?;
+        let jobs = github::list_run_jobs(token, owner, repo_name, run_id)?;
+
+        let mut running = Vec::new();
+        for job in &jobs {
+            if reported.contains(&job.id) {
+                continue;
+            }
+            if job.status != "completed" {
+                started.entry(job.id).or_insert_with(Instant::now);
+                running.push(progress(job));
+                continue;
+            }
+
+            let symbol = symbol(Some(&job.status), job.conclusion.as_deref());
+            board.clear();
+            match started.get(&job.id) {
+                Some(since) => info!("{} {} ({}s)", symbol, job.name, since.elapsed().as_secs()),
+                None => info!("{} {}", symbol, job.name),
+            }
+            reported.insert(job.id);
+        }
+        board.set(running);
+
+        if run.status.as_deref() == Some("completed") && jobs.iter().all(|job| reported.contains(&job.id)) {
+            return Ok(run);
+        }
+
+        let next_poll = Instant::now() + interval;
+        while Instant::now() < next_poll {
+            if cancel_scope.is_cancelled() {
+                board.clear();
+                return Err(anyhow::anyhow!("Stopped watching, the run continues on GitHub"));
+            }
+            board.draw();
+            thread::sleep(REDRAW_INTERVAL);
+        }
+    }
+}
+
+/// The jobs still running, one spinner line each on stderr. Redrawing moves
+/// the cursor back up over the block, so lines are cut short to never wrap.
+struct JobBoard {
+    lines: Vec<String>,
+    drawn: usize,
+    frame: usize,
+    visible: bool,
+}
+
+impl JobBoard {
+    fn new() -> JobBoard {
+        JobBoard {
+            lines: Vec::new(),
+            drawn: 0,
+            frame: 0,
+            visible: io::stderr().is_terminal() && matches!(output::format(), OutputFormat::Text | OutputFormat::Markdown),
+        }
+    }
+
+    fn set(&mut self, lines: Vec<String>) {
+        self.lines = lines.iter().map(|line| super::truncate(line, MAX_LINE_WIDTH)).collect();
+    }
+
+    fn draw(&mut self) {
+        if !self.visible {
+            return;
+        }
+        self.clear();
+        self.frame = (self.frame + 1) % SPINNER_FRAMES.len();
+
+        let mut stderr = io::stderr().lock();
+        for line in &self.lines {
+            let _ = writeln!(stderr, "{} {}", SPINNER_FRAMES[self.frame].blue(), line);
+        }
+        self.drawn = self.lines.len();
+    }
+
+    /// Takes the block off the screen, e.g. to print a finished job above it.
+    fn clear(&mut self) {
+        if self.drawn > 0 {
+            let _ = write!(io::stderr(), "\x1b[{}A\r\x1b[J", self.drawn);
+            self.drawn = 0;
+        }
+    }
+}
+
+impl Drop for JobBoard {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn progress(job: &Job) -> String {
+    let running = job.steps.iter().find(|step| step.status == "in_progress");
+    match running {
+        Some(step) => format!("{} — {} ({}/{})", job.name, step.name, step.number, job.steps.len()),
+        None => format!("{} — {}", job.name, job.status.replace('_', " ")),
+    }
+}
+
+/// Streams the log of each job to stdout, headed by the job's name on stderr
+/// so the logs themselves can be piped.
+pub fn stream_logs(token: &str, owner: &str, repo_name: &str, jobs: &[Job]) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    for job in jobs {
+        info!("{} {}", symbol(Some(&job.status), job.conclusion.as_deref()), job.name.bold());
+        if job.status != "completed" {
+            warn!("Logs of {} are available once it completes", job.name);
+            continue;
+        }
+
+        let mut response = github::download_job_logs(token, owner, repo_name, job.id)?;
+        io::copy(&mut response, &mut stdout)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_time_drops_seconds_and_zone() {
+        assert_eq!(short_time("2026-10-19T08:12:44Z"), "2026-10-19 08:12");
+        assert!(succeeded(Some("skipped")));
+        assert!(!succeeded(Some("cancelled")));
+        assert!(!succeeded(None));
+    }
+}
@@ -1,27 +1,39 @@
-use std::io::{self, Write};
+use std::collections::{BTreeMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
 use std::thread;
+use std::time::Duration;
 
 use anyhow::Result;
+use colored::*;
 use inquire::Select;
 use serde::{Deserialize, Serialize};
-use spinoff::{spinners, Spinner, Color};
-use log::error;
+use spinoff::{spinners, Spinner, Color, Streams};
+use log::{error, warn};
 
-use crate::config;
-use crate::http;
+use crate::config::{self, AiSettings};
+use crate::http::{self, SendWithRetry};
+use crate::utils::cancel;
 
-const NUM_SUGGESTIONS: usize = 3;
-const TEMPERATURE: f32 = 0.8;
-const MAX_REGENERATIONS: usize = 3;
 const MAX_CONTEXT_LEN: usize = 500;
+const MAX_TOKENS_PER_SUGGESTION: u32 = 60;
+// A rough size of a token in characters, to keep the context within the
+// model's budget.
+const CHARS_PER_TOKEN: usize = 4;
+const MIN_CONTEXT_CHARS: usize = 1_000;
 
-const OPEN_AI_BASE_URL: &str = "https://api.openai.com/v1/chat/completions";
+const CHAT_COMPLETIONS_PATH: &str = "/chat/completions";
+const SSE_DATA_PREFIX: &str = "data:";
+const SSE_DONE: &str = "[DONE]";
 
 #[derive(Serialize)]
 struct OpenApiRequest {
     model: String,
     temperature: f32,
-    max_tokens: i32,
+    max_tokens: u32,
+    n: usize,
+    stream: bool,
     messages: Vec<Message>,
 }
 
@@ -32,18 +44,21 @@ pub struct Message {
 }
 
 #[derive(Deserialize)]
-struct OpenApiResponse {
-    choices: Vec<Choice>,
+struct OpenApiStreamChunk {
+    choices: Vec<StreamChoice>,
 }
 
 #[derive(Deserialize)]
-struct Choice {
-    message: ResponseMessage,
+struct StreamChoice {
+    #[serde(default)]
+    index: usize,
+    delta: Delta,
+    finish_reason: Option<String>,
 }
 
 #[derive(Deserialize)]
-struct ResponseMessage {
-    content: String,
+struct Delta {
+    content: Option<String>,
 }
 
 fn get_system_prompt_message() -> Message {
@@ -53,6 +68,13 @@ fn get_system_prompt_message() -> Message {
     }
 }
 
+fn get_list_system_prompt_message(count: usize) -> Message {
+    Message {
+        role: "system".to_string(),
+        content: format!("You rewrite git commit messages to be professional and follow Conventional Commits. Its ok if you skip scope but try to figure out. Produce {} distinct alternatives. Output only a JSON array of strings. No explanations.", count)
+    }
+}
+
 fn get_user_prompt_message(message: &str) -> Message {
     Message { 
         role: "user".to_string(),
@@ -66,8 +88,8 @@ fn get_or_prompt_api_key() -> Result<String> {
         return Ok(key);
     }
 
-    print!("Enter your OpenAI API key: ");
-    io::stdout().flush()?;
+    eprint!("Enter your OpenAI API key: ");
+    io::stderr().flush()?;
 
     let mut api_key = String::new();
     io::stdin().read_line(&mut api_key)?;
@@ -81,18 +103,58 @@ fn get_or_prompt_api_key() -> Result<String> {
     Ok(api_key)
 }
 
-fn fetch_single_suggestion(client: &reqwest::blocking::Client, api_key: &str, original_msg: &str) -> Result<String> {
+/// Per-invocation overrides for the `ai` section of the config.
+#[derive(Default)]
+pub struct SuggestionOverrides {
+    pub model: Option<String>,
+    pub count: Option<usize>,
+    pub temperature: Option<f32>,
+}
+
+impl SuggestionOverrides {
+    fn apply(&self, mut settings: AiSettings) -> AiSettings {
+        if let Some(model) = &self.model {
+            settings.model = model.clone();
+        }
+        if let Some(count) = self.count {
+            settings.suggestions = count.max(1);
+        }
+        if let Some(temperature) = self.temperature {
+            settings.temperature = temperature;
+        }
+        settings
+    }
+}
+
+/// Outcome of one candidate in a suggestion request, sent as soon as that
+/// candidate finishes streaming.
+enum SuggestionEvent {
+    Completed(String),
+    Failed(anyhow::Error),
+}
+
+fn request_suggestions(client: &reqwest::blocking::Client, api_key: &str, original_msg: &str, settings: &AiSettings, cancelled: &AtomicBool, on_event: &dyn Fn(SuggestionEvent)) -> Result<()> {
+    let (system_message, n, max_tokens) = if settings.multiple_choices {
+        (get_system_prompt_message(), settings.suggestions, MAX_TOKENS_PER_SUGGESTION)
+    } else {
+        let max_tokens = MAX_TOKENS_PER_SUGGESTION * settings.suggestions as u32 + 20;
+        (get_list_system_prompt_message(settings.suggestions), 1, max_tokens)
+    };
+
     let request = OpenApiRequest {
-        model: "gpt-4.1-mini".to_string(),
-        temperature: TEMPERATURE,
-        max_tokens: 60,
-        messages: vec![get_system_prompt_message(), get_user_prompt_message(original_msg)],
+        model: settings.model.clone(),
+        temperature: settings.temperature,
+        max_tokens,
+        n,
+        stream: true,
+        messages: vec![system_message, get_user_prompt_message(original_msg)],
     };
 
-    let response = client.post(OPEN_AI_BASE_URL)
+    let url = format!("{}{}", config::endpoints().openai_api, CHAT_COMPLETIONS_PATH);
+    let response = client.post(&url)
         .json(&request)
         .header("Authorization", format!("Bearer {}", api_key))
-        .send()?;
+        .send_with_retry()?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -101,61 +163,178 @@ fn fetch_single_suggestion(client: &reqwest::blocking::Client, api_key: &str, or
         return Err(anyhow::anyhow!("Failed to get polished commit message"));
     }
 
-    let api_response: OpenApiResponse = response.json()?;
-    let content = api_response.choices.get(0)
-        .ok_or_else(|| anyhow::anyhow!("No choices in response"))?
-        .message.content.trim().to_string();
+    let reader = BufReader::new(response);
+    if settings.multiple_choices {
+        return read_event_stream(reader, cancelled, &mut |_, content| on_event(complete(content)));
+    }
 
-    Ok(content)
+    let mut list = String::new();
+    read_event_stream(reader, cancelled, &mut |_, content| list = content)?;
+    for suggestion in parse_suggestion_list(&list)? {
+        on_event(complete(suggestion));
+    }
+    Ok(())
 }
 
-const ADD_CONTEXT_OPTION: &str = "↻ Regenerate with more context...";
+fn complete(content: String) -> SuggestionEvent {
+    if content.is_empty() {
+        SuggestionEvent::Failed(anyhow::anyhow!("No content in response"))
+    } else {
+        SuggestionEvent::Completed(content)
+    }
+}
 
-fn fetch_suggestions(api_key: &str, message: &str) -> Vec<String> {
-    let mut spinner = Spinner::new(spinners::BouncingBar, "Generating commit message suggestions...", Color::Blue);
+/// Accumulates the `delta.content` fragments of an OpenAI server-sent event
+/// stream per choice, calling `on_choice` as each choice finishes. Dropping
+/// the reader early closes the connection, which is how a cancelled request
+/// is aborted.
+fn read_event_stream(reader: impl BufRead, cancelled: &AtomicBool, on_choice: &mut dyn FnMut(usize, String)) -> Result<()> {
+    let mut pending: BTreeMap<usize, String> = BTreeMap::new();
 
-    let handles: Vec<_> = (0..NUM_SUGGESTIONS)
-        .map(|_| {
-            let api_key = api_key.to_string();
-            let msg = message.to_string();
-            thread::spawn(move || {
-                let client = http::get_client();
-                fetch_single_suggestion(client, &api_key, &msg)
-            })
-        })
-        .collect();
+    for line in reader.lines() {
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(anyhow::anyhow!("Request cancelled"));
+        }
 
+        let line = line?;
+        let Some(data) = line.strip_prefix(SSE_DATA_PREFIX) else {
+            continue;
+        };
+        let data = data.trim();
+        if data == SSE_DONE {
+            break;
+        }
+
+        let chunk: OpenApiStreamChunk = serde_json::from_str(data)?;
+        for choice in chunk.choices {
+            let content = pending.entry(choice.index).or_default();
+            if let Some(fragment) = choice.delta.content {
+                content.push_str(&fragment);
+            }
+            if choice.finish_reason.is_some() {
+                let content = pending.remove(&choice.index).unwrap_or_default();
+                on_choice(choice.index, content.trim().to_string());
+            }
+        }
+    }
+
+    // Streams cut short without a finish_reason still carry usable text.
+    for (index, content) in pending {
+        on_choice(index, content.trim().to_string());
+    }
+
+    Ok(())
+}
+
+/// The JSON in a completion, which models tend to wrap in a Markdown code
+/// fence even when asked not to.
+pub fn strip_code_fence(content: &str) -> &str {
+    content
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+}
+
+fn parse_suggestion_list(content: &str) -> Result<Vec<String>> {
+    let suggestions: Vec<String> = serde_json::from_str(strip_code_fence(content))
+        .map_err(|e| anyhow::anyhow!("Could not parse suggestion list: {}", e))?;
+    Ok(suggestions.into_iter().map(|s| s.trim().to_string()).collect())
+}
+
+/// Lowercases and drops punctuation so that suggestions differing only in
+/// casing, trailing periods or spacing count as duplicates.
+fn normalize_suggestion(message: &str) -> String {
+    message
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const ADD_CONTEXT_OPTION: &str = "↻ Regenerate with more context...";
+const GENERATING_MESSAGE: &str = "Generating commit message suggestions...";
+
+fn fetch_suggestions(api_key: &str, message: &str, settings: &AiSettings) -> Result<Vec<String>> {
+    let cancel_scope = cancel::scope()?;
+    let (tx, rx) = mpsc::channel();
+
+    {
+        let api_key = api_key.to_string();
+        let msg = message.to_string();
+        let settings = settings.clone();
+        let cancelled = cancel_scope.flag();
+        thread::spawn(move || {
+            let client = http::get_ai_client();
+            let send = |event| {
+                let _ = tx.send(event);
+            };
+            if let Err(e) = request_suggestions(client, &api_key, &msg, &settings, &cancelled, &send) {
+                send(SuggestionEvent::Failed(e));
+            }
+        });
+    }
+
+    let mut spinner = Spinner::new_with_stream(spinners::BouncingBar, GENERATING_MESSAGE, Color::Blue, Streams::Stderr);
     let mut suggestions: Vec<String> = Vec::new();
-    for handle in handles {
-        match handle.join() {
-            Ok(Ok(msg)) => {
-                if !suggestions.contains(&msg) {
+    let mut seen = HashSet::new();
+    let mut failures = 0;
+
+    loop {
+        if cancel_scope.is_cancelled() {
+            if suggestions.is_empty() {
+                spinner.stop();
+                return Err(anyhow::anyhow!("Cancelled"));
+            }
+            warn!("Cancelled, showing the {} suggestion(s) received so far", suggestions.len());
+            break;
+        }
+
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(SuggestionEvent::Completed(msg)) => {
+                if seen.insert(normalize_suggestion(&msg)) {
+                    spinner.stop_and_persist(&"•".cyan().to_string(), &msg);
                     suggestions.push(msg);
+                    spinner = Spinner::new_with_stream(spinners::BouncingBar, GENERATING_MESSAGE, Color::Blue, Streams::Stderr);
                 }
             }
-            Ok(Err(e)) => error!("Failed to fetch suggestion: {}", e),
-            Err(_) => error!("Thread panicked"),
+            Ok(SuggestionEvent::Failed(e)) => {
+                failures += 1;
+                error!("Failed to fetch suggestion: {}", e);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
         }
     }
 
     spinner.stop();
-    suggestions
+
+    if failures > 0 && !suggestions.is_empty() {
+        warn!("Some suggestions failed, showing {} partial result(s)", suggestions.len());
+    }
+
+    Ok(suggestions)
 }
 
-pub fn get_polished_commit_msg(original_msg: &str) -> Result<String> {
+pub fn get_polished_commit_msg(original_msg: &str, overrides: &SuggestionOverrides) -> Result<String> {
     let api_key = get_or_prompt_api_key()?;
+    let settings = overrides.apply(config::load_ai_settings()?);
     let mut current_msg = original_msg.to_string();
     let mut regeneration_count = 0;
 
     loop {
-        if regeneration_count >= MAX_REGENERATIONS {
+        if regeneration_count >= settings.max_regenerations {
             return Err(anyhow::anyhow!(
                 "Maximum regeneration attempts ({}) reached",
-                MAX_REGENERATIONS
+                settings.max_regenerations
             ));
         }
 
-        let suggestions = fetch_suggestions(&api_key, &current_msg);
+        let suggestions = fetch_suggestions(&api_key, &current_msg, &settings)?;
         regeneration_count += 1;
 
         if suggestions.is_empty() {
@@ -172,8 +351,8 @@ pub fn get_polished_commit_msg(original_msg: &str) -> Result<String> {
             .map_err(|e| anyhow::anyhow!("Selection cancelled: {}", e))?;
 
         if selected == ADD_CONTEXT_OPTION {
-            print!("Additional context: ");
-            io::stdout().flush()?;
+            eprint!("Additional context: ");
+            io::stderr().flush()?;
 
             let mut extra_context = String::new();
             io::stdin().read_line(&mut extra_context)?;
@@ -199,4 +378,125 @@ pub fn get_polished_commit_msg(original_msg: &str) -> Result<String> {
 
         return Ok(selected);
     }
-}
\ No newline at end of file
+}
+/// How many characters of input fit in `context_tokens` next to
+/// `system_prompt`, though never fewer than a useful minimum.
+pub fn context_budget(context_tokens: usize, system_prompt: &str) -> usize {
+    (context_tokens * CHARS_PER_TOKEN).saturating_sub(system_prompt.len()).max(MIN_CONTEXT_CHARS)
+}
+
+/// Single completion for `input` under `system_prompt`, shown with a
+/// spinner while it streams in. Used where one answer is wanted rather than
+/// suggestions to pick from.
+pub fn generate(system_prompt: &str, input: &str, max_tokens: u32, model: Option<&str>, spinner_message: &str) -> Result<String> {
+    let api_key = get_or_prompt_api_key()?;
+    let overrides = SuggestionOverrides { model: model.map(str::to_string), ..Default::default() };
+    let settings = overrides.apply(config::load_ai_settings()?);
+    let cancel_scope = cancel::scope()?;
+    let (tx, rx) = mpsc::channel();
+
+    {
+        let request = OpenApiRequest {
+            model: settings.model.clone(),
+            temperature: settings.temperature,
+            max_tokens,
+            n: 1,
+            stream: true,
+            messages: vec![
+                Message { role: "system".to_string(), content: system_prompt.to_string() },
+                get_user_prompt_message(input),
+            ],
+        };
+        let cancelled = cancel_scope.flag();
+        thread::spawn(move || {
+            let _ = tx.send(request_completion(&api_key, &request, &cancelled));
+        });
+    }
+
+    let mut spinner = Spinner::new_with_stream(spinners::BouncingBar, spinner_message.to_string(), Color::Blue, Streams::Stderr);
+    loop {
+        if cancel_scope.is_cancelled() {
+            spinner.stop();
+            return Err(anyhow::anyhow!("Cancelled"));
+        }
+
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(result) => {
+                spinner.stop();
+                let content = result?;
+                if content.is_empty() {
+                    return Err(anyhow::anyhow!("No content in response"));
+                }
+                return Ok(content);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                spinner.stop();
+                return Err(anyhow::anyhow!("Request ended without a response"));
+            }
+        }
+    }
+}
+
+fn request_completion(api_key: &str, request: &OpenApiRequest, cancelled: &AtomicBool) -> Result<String> {
+    let url = format!("{}{}", config::endpoints().openai_api, CHAT_COMPLETIONS_PATH);
+    let response = http::get_ai_client().post(&url)
+        .json(request)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send_with_retry()?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().unwrap_or_default();
+        error!("API error {}: {}", status, body);
+        return Err(anyhow::anyhow!("AI request failed"));
+    }
+
+    let mut content = String::new();
+    read_event_stream(BufReader::new(response), cancelled, &mut |_, choice| content = choice)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn fetch_suggestions_streams_choices_and_dedups() {
+        let server = test_support::server();
+        server.route("POST", "/openai/v1/chat/completions", &["openai/chat_stream"], Some("https://api.openai.com/v1/chat/completions"));
+
+        let suggestions = fetch_suggestions("test-key", "added ip cmd", &AiSettings::default()).unwrap();
+        assert_eq!(suggestions, vec!["feat(cli): add ip command", "fix(ip): handle missing location"]);
+
+        let request = &server.requests_to("/openai/v1/chat/completions")[0];
+        assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["n"], 3);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn read_event_stream_stops_when_cancelled() {
+        let stream = "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"feat\"},\"finish_reason\":null}]}\n\n";
+        let cancelled = AtomicBool::new(true);
+
+        let result = read_event_stream(stream.as_bytes(), &cancelled, &mut |_, _| {});
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_suggestion_list_accepts_fenced_json() {
+        let content = "```json\n[\"feat: add ip\", \" fix: typo \"]\n```";
+        assert_eq!(parse_suggestion_list(content).unwrap(), vec!["feat: add ip", "fix: typo"]);
+    }
+
+    #[test]
+    fn normalize_suggestion_ignores_case_and_punctuation() {
+        assert_eq!(
+            normalize_suggestion("Feat(cli): add  IP command."),
+            normalize_suggestion("feat(cli): add ip command")
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::env;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{ai, git};
+
+const ASK_SYSTEM_PROMPT: &str = "You turn requests into a single shell command, preferring git where it fits. Use the repository context to fill in branch names and paths. Output only a JSON object with the fields command (one line, runnable with sh), explanation (one or two sentences on what it does) and destructive (true if it discards work, rewrites published history or deletes files or branches). No explanations outside the JSON.";
+const MAX_ASK_TOKENS: u32 = 300;
+
+// Commands that lose work, checked on top of the model's own judgement.
+const DESTRUCTIVE_PATTERNS: &[&str] = &[
+    "reset --hard",
+    "push --force",
+    "push -f",
+    "push --delete",
+    "clean -f",
+    "clean -df",
+    "clean -xf",
+    "branch -D",
+    "checkout -- ",
+    "checkout .",
+    "restore ",
+    "stash drop",
+    "stash clear",
+    "filter-branch",
+    "filter-repo",
+    "reflog expire",
+    "gc --prune",
+    "update-ref -d",
+    "rm ",
+];
+// Subcommands whose flags are checked one at a time, as they can come in
+// any order and be grouped, like `clean -d -f` or `clean -df`.
+const DESTRUCTIVE_FLAGS: &[(&str, &[&str])] = &[
+    ("push", &["-f", "--force", "--force-with-lease", "--force-if-includes", "-d", "--delete", "--mirror", "--prune"]),
+    ("clean", &["-f", "--force"]),
+    ("checkout", &["-f", "--force"]),
+    ("switch", &["-f", "--force", "--discard-changes"]),
+    ("find", &["-delete"]),
+];
+const SEPARATORS: &[&str] = &[";", "&&", "||", "|", "&"];
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Suggestion {
+    pub command: String,
+    pub explanation: String,
+    #[serde(default)]
+    pub destructive: bool,
+}
+
+/// Asks for a command doing what `request` describes, telling the model
+/// about the repository in the current directory.
+pub fn suggest(request: &str, model: Option<&str>) -> Result<Suggestion> {
+    let input = format!("{}\n\nRequest: {}", context(), request);
+    let answer = ai::generate(ASK_SYSTEM_PROMPT, &input, MAX_ASK_TOKENS, model, "Thinking...")?;
+    let mut suggestion: Suggestion = serde_json::from_str(ai::strip_code_fence(&answer))
+        .map_err(|e| anyhow::anyhow!("Could not read the suggested command: {}", e))?;
+
+    suggestion.command = suggestion.command.trim().to_string();
+    if suggestion.command.is_empty() {
+        return Err(anyhow::anyhow!("No command was suggested"));
+    }
+    suggestion.destructive |= is_destructive(&suggestion.command);
+    Ok(suggestion)
+}
+
+fn context() -> String {
+    let shell = env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let mut context = format!("OS: {}\nShell: {}\n", env::consts::OS, shell);
+    match git::status_short() {
+        Some(status) => {
+            let branch = git::current_branch().unwrap_or_else(|| "(detached HEAD)".to_string());
+            context.push_str(&format!("Current branch: {}\ngit status --short --branch:\n{}\n", branch, status));
+        }
+        None => context.push_str("Not inside a git repository\n"),
+    }
+    context
+}
+
+/// Whether `command` looks like it loses work. Only ever a reason to warn:
+/// a command that passes is not known to be safe.
+pub fn is_destructive(command: &str) -> bool {
+    // Spacing is normalized so `git  push  -f` is caught too.
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let normalized = format!("{} ", words.join(" "));
+    DESTRUCTIVE_PATTERNS.iter().any(|pattern| normalized.contains(pattern))
+        || DESTRUCTIVE_FLAGS.iter().any(|(name, flags)| arguments(&words, name).any(|arg| has_flag(arg, flags)))
+        || arguments(&words, "push").any(|arg| (arg.starts_with('+') || arg.starts_with(':')) && arg.len() > 1)
+        || truncates_file(command)
+}
+
+/// The words following each use of `name`, up to the end of its command.
+fn arguments<'a>(words: &'a [&'a str], name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    words
+        .iter()
+        .enumerate()
+        .filter(move |(_, word)| **word == name)
+        .flat_map(move |(index, _)| words[index + 1..].iter().take_while(|word| !SEPARATORS.contains(word)))
+        .copied()
+}
+
+/// Whether `arg` is one of `flags`, or a group of short flags such as `-df`
+/// that includes one.
+fn has_flag(arg: &str, flags: &[&str]) -> bool {
+    if flags.contains(&arg) {
+        return true;
+    }
+    match arg.strip_prefix('-') {
+        Some(group) if !group.starts_with('-') => group.chars().any(|c| flags.contains(&format!("-{}", c).as_str())),
+        _ => false,
+    }
+}
+
+/// `> file` empties the file; `>>` appends and `>&2` only redirects to
+/// another descriptor.
+fn truncates_file(command: &str) -> bool {
+    let mut rest = command;
+    while let Some(index) = rest.find('>') {
+        rest = &rest[index + 1..];
+        if let Some(after) = rest.strip_prefix(['>', '&']) {
+            rest = after;
+            continue;
+        }
+        let target = rest.trim_start_matches('|').split_whitespace().next().unwrap_or_default();
+        if target != "/dev/null" {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_destructive_catches_lost_work() {
+        assert!(is_destructive("git reset --hard HEAD~1"));
+        assert!(is_destructive("git  push  --force origin main"));
+        assert!(is_destructive("git branch -D feature"));
+        assert!(is_destructive("rm -rf target"));
+        assert!(is_destructive("git push origin +main"));
+        assert!(is_destructive("git push origin :old-branch"));
+        assert!(is_destructive("git push origin main --force-with-lease"));
+        assert!(is_destructive("git checkout -f"));
+        assert!(is_destructive("git clean -d -f"));
+        assert!(is_destructive("find . -name '*.orig' -delete"));
+        assert!(is_destructive("> notes.txt"));
+        assert!(is_destructive("git show HEAD:README.md >README.md"));
+        assert!(!is_destructive("git reset --soft HEAD~1"));
+        assert!(!is_destructive("git log --oneline -5"));
+        assert!(!is_destructive("git push origin main && git clean -n -d"));
+        assert!(!is_destructive("git log -1 >> notes.txt 2>&1"));
+        assert!(!is_destructive("git fetch --all 2>/dev/null"));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::commands::{ai, git};
+use crate::config;
+
+const EXPLAIN_CHANGE_PROMPT: &str = "You explain changes in a git repository to a developer reviewing unfamiliar history. Say concisely what changed and why, taking the why from the commit messages and saying so when the reason is unclear rather than guessing. Use Markdown with short paragraphs or bullets, under 250 words. No preamble.";
+const EXPLAIN_FILE_PROMPT: &str = "You explain code to a developer new to a git repository. From the blame and the file's history, say concisely what the code does and how it came to be, naming the commits that shaped it. Use Markdown with short paragraphs or bullets, under 250 words. No preamble.";
+const MAX_EXPLAIN_TOKENS: u32 = 600;
+const FILE_HISTORY_LIMIT: usize = 15;
+const TRUNCATED: &str = "\n[truncated]\n";
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Subject {
+    Commit { rev: String },
+    Range { range: String },
+    File { path: String, lines: Option<(u32, u32)> },
+}
+
+/// Works out whether `target` is a range, a file (optionally with `:line`
+/// or `:start-end`) or a revision, in that order.
+pub fn resolve(target: &str) -> Result<Subject> {
+    if target.contains("..") && !Path::new(target).exists() {
+        return Ok(Subject::Range { range: target.to_string() });
+    }
+    let (path, lines) = split_lines(target);
+    if Path::new(path).is_file() {
+        return Ok(Subject::File { path: path.to_string(), lines });
+    }
+    if git::is_commit(target) {
+        return Ok(Subject::Commit { rev: target.to_string() });
+    }
+    Err(anyhow::anyhow!("{} is not a revision, range or file", target))
+}
+
+/// Splits a trailing `:<line>` or `:<start>-<end>` off `target`.
+fn split_lines(target: &str) -> (&str, Option<(u32, u32)>) {
+    let Some((path, spec)) = target.rsplit_once(':') else {
+        return (target, None);
+    };
+    let lines = match spec.split_once('-') {
+        Some((start, end)) => start.parse().ok().zip(end.parse().ok()),
+        None => spec.parse().ok().map(|line| (line, line)),
+    };
+    match lines {
+        Some((start, end)) if start > 0 && start <= end => (path, Some((start, end))),
+        _ => (target, None),
+    }
+}
+
+/// Explains `subject` from what git knows about it, as Markdown.
+pub fn explain(subject: &Subject, model: Option<&str>) -> Result<String> {
+    let context_tokens = config::load_ai_settings()?.context_tokens;
+    let (prompt, context, message) = match subject {
+        Subject::Commit { rev } => (EXPLAIN_CHANGE_PROMPT, git::show(rev)?, format!("Explaining {}...", rev)),
+        Subject::Range { range } => {
+            let context = format!("Commits:\n{}\nDiff:\n{}", git::commit_messages(range)?, git::diff(Some(range))?);
+            (EXPLAIN_CHANGE_PROMPT, context, format!("Explaining {}...", range))
+        }
+        Subject::File { path, lines } => {
+            // Git rejects ranges past the end of the file.
+            let lines = lines.map(|(start, end)| (start, end.min(line_count(path).max(start))));
+            let context = format!(
+                "History of {}:\n{}\nBlame:\n{}",
+                path,
+                git::file_history(path, FILE_HISTORY_LIMIT)?,
+                git::blame(path, lines)?
+            );
+            (EXPLAIN_FILE_PROMPT, context, format!("Explaining {}...", path))
+        }
+    };
+
+    let budget = ai::context_budget(context_tokens, prompt);
+    ai::generate(prompt, &truncate(&context, budget), MAX_EXPLAIN_TOKENS, model, &message)
+}
+
+fn line_count(path: &str) -> u32 {
+    fs::read_to_string(path).map(|content| content.lines().count() as u32).unwrap_or(u32::MAX)
+}
+
+fn truncate(context: &str, budget: usize) -> String {
+    if context.len() <= budget {
+        return context.to_string();
+    }
+    let mut end = budget.saturating_sub(TRUNCATED.len());
+    while !context.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}", &context[..end], TRUNCATED)
+}
+
+/// Markdown as plain text for the terminal: heading markers, emphasis and
+/// code fences are dropped, the text itself is kept. Fenced code is kept
+/// exactly as written.
+pub fn render_plain(markdown: &str) -> String {
+    let mut in_fence = false;
+    let mut lines = Vec::new();
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_string());
+            continue;
+        }
+        // Every other piece between backticks is code, kept as written.
+        let plain = strip_heading(line)
+            .split('`')
+            .enumerate()
+            .map(|(index, piece)| if index % 2 == 1 { piece.to_string() } else { strip_emphasis(piece) })
+            .collect::<String>();
+        lines.push(plain);
+    }
+    lines.join("\n")
+}
+
+/// Drops the `#` to `######` marking a heading, but not a `#` starting text
+/// such as an issue number.
+fn strip_heading(line: &str) -> &str {
+    let text = line.trim_start_matches('#');
+    let level = line.len() - text.len();
+    match text.strip_prefix(' ') {
+        Some(heading) if (1..=6).contains(&level) => heading.trim_start(),
+        _ => line,
+    }
+}
+
+/// Drops `**` and `__` where they wrap words, leaving alone markers inside
+/// words, as in `snake__case`, and identifiers such as `__init__`.
+fn strip_emphasis(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text;
+    while let Some((before, after)) = ["**", "__"]
+        .iter()
+        .filter_map(|marker| rest.find(marker))
+        .min()
+        .map(|start| rest.split_at(start))
+    {
+        let (marker, inner) = after.split_at(2);
+        let opens = before.chars().last().is_none_or(|c| c.is_whitespace() || "([{\"'".contains(c))
+            && inner.starts_with(|c: char| !c.is_whitespace());
+        let wrapped = opens
+            .then(|| closing(inner, marker))
+            .flatten()
+            .filter(|&end| marker == "**" || !inner[..end].chars().all(|c| c.is_alphanumeric() || c == '_'));
+
+        plain.push_str(before);
+        match wrapped {
+            Some(end) => {
+                plain.push_str(&inner[..end]);
+                rest = &inner[end + marker.len()..];
+            }
+            None => {
+                plain.push_str(marker);
+                rest = inner;
+            }
+        }
+    }
+    plain.push_str(rest);
+    plain
+}
+
+/// Where `marker` closes emphasis opened just before `text`: right after a
+/// word and not followed by more of one.
+fn closing(text: &str, marker: &str) -> Option<usize> {
+    text.match_indices(marker).map(|(index, _)| index).find(|&index| {
+        let before = text[..index].chars().last();
+        let after = text[index + marker.len()..].chars().next();
+        before.is_some_and(|c| !c.is_whitespace()) && after.is_none_or(|c| !c.is_alphanumeric() && c != '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_lines_reads_single_lines_and_ranges() {
+        assert_eq!(split_lines("src/main.rs:42"), ("src/main.rs", Some((42, 42))));
+        assert_eq!(split_lines("src/main.rs:10-20"), ("src/main.rs", Some((10, 20))));
+        assert_eq!(split_lines("src/main.rs:20-10"), ("src/main.rs:20-10", None));
+        assert_eq!(split_lines("HEAD~2"), ("HEAD~2", None));
+    }
+
+    #[test]
+    fn render_plain_drops_markdown_syntax() {
+        let markdown = "## Summary\n\nAdds **retries** to `send`.\n```rust\nsend_with_retry()\n```";
+        assert_eq!(render_plain(markdown), "Summary\n\nAdds retries to send.\nsend_with_retry()");
+
+        let markdown = "Calls `__init__` from __init__.py, **not** my__private__helper, __twice over__.";
+        assert_eq!(render_plain(markdown), "Calls __init__ from __init__.py, not my__private__helper, twice over.");
+    }
+
+    #[test]
+    fn render_plain_keeps_hashes_that_are_not_headings() {
+        assert_eq!(render_plain("#123 fixed the retry loop"), "#123 fixed the retry loop");
+        assert_eq!(render_plain("### Why"), "Why");
+
+        let markdown = "Run:\n```sh\n# install first\nmake **all**\n```";
+        assert_eq!(render_plain(markdown), "Run:\n# install first\nmake **all**");
+    }
+}
@@ -0,0 +1,65 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use anyhow::Result;
+use log::debug;
+
+const DEFAULT_EDITOR: &str = "vi";
+
+/// The ID of a gist given as an ID or as its URL, e.g.
+/// `https://gist.github.com/octocat/aa5a315d61ae9438b18d`.
+pub fn gist_id(gist: &str) -> &str {
+    let gist = gist.split(['#', '?']).next().unwrap_or(gist).trim_end_matches('/');
+    gist.rsplit('/').next().unwrap_or(gist)
+}
+
+/// Lets the user edit `content` in `$VISUAL` or `$EDITOR`, returning what
+/// they saved. The temporary file keeps `name` so editors pick the right
+/// syntax highlighting.
+pub fn edit_in_editor(gist_id: &str, name: &str, content: &str) -> Result<String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    // A fresh directory only the user can enter, created exclusively so no
+    // one else can plant or swap the file the editor opens.
+    let prefix = format!("kit-gist-{}-", gist_id);
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(&prefix);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o700));
+    }
+    let dir = builder.tempdir()?;
+    let path = dir.path().join(name);
+    fs::write(&path, content)?;
+
+    // Through `sh` so editors configured with arguments, like `code --wait`, work.
+    debug!("Editing {} with {}", path.display(), editor);
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(&path)
+        .status()?;
+    let edited = fs::read_to_string(&path);
+    drop(dir);
+
+    if !status.success() {
+        return Err(anyhow::anyhow!("{} exited with {}, gist left unchanged", editor, status));
+    }
+    Ok(edited?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gist_id_accepts_ids_and_urls() {
+        assert_eq!(gist_id("aa5a315d61ae9438b18d"), "aa5a315d61ae9438b18d");
+        assert_eq!(gist_id("https://gist.github.com/octocat/aa5a315d61ae9438b18d"), "aa5a315d61ae9438b18d");
+        assert_eq!(gist_id("https://gist.github.com/octocat/aa5a315d61ae9438b18d/#file-hello-rs"), "aa5a315d61ae9438b18d");
+    }
+}
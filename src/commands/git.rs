@@ -1,14 +1,23 @@
-use std::process::{Command, ExitStatus};
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
 use anyhow::Result;
 use log::{info, error};
 use colored::*;
 use crate::commands::github::GetRepoResponse;
 
-pub fn clone_repository(repo_details: &GetRepoResponse) -> Result<ExitStatus> {
+pub fn clone_repository(repo_details: &GetRepoResponse, branch: Option<&str>) -> Result<ExitStatus> {
     info!("Cloning from {}", repo_details.ssh_url.bright_black());
 
-    let clone_status = Command::new("git")
-        .args(&["clone", &repo_details.ssh_url])
+    let mut command = Command::new("git");
+    command.arg("clone");
+    if let Some(branch) = branch {
+        command.args(["--branch", branch]);
+    }
+
+    // git's own output goes to stderr so stdout stays machine-readable.
+    let clone_status = command
+        .arg(&repo_details.ssh_url)
+        .stdout(io::stderr())
         .status()?;
 
     if !clone_status.success() {
@@ -26,6 +35,7 @@ pub fn add_upstream(repo_name: &str, parent_url: &str) -> Result<ExitStatus> {
     let upstream_status = Command::new("git")
         .args(&["remote", "add", "upstream", &parent_url])
         .current_dir(repo_name)
+        .stdout(io::stderr())
         .status()?;
 
     if upstream_status.success() {
@@ -48,6 +58,7 @@ pub fn is_git_repo() -> bool {
 pub fn add_all() -> Result<ExitStatus> {
     let status = Command::new("git")
         .args(&["add", "."])
+        .stdout(io::stderr())
         .status()?;
 
     if !status.success() {
@@ -60,6 +71,7 @@ pub fn add_all() -> Result<ExitStatus> {
 pub fn commit_with_message(message: &str) -> Result<ExitStatus> {
     let status = Command::new("git")
         .args(&["commit", "-m", message])
+        .stdout(io::stderr())
         .status()?;
 
     if status.success() {
@@ -70,3 +82,251 @@ pub fn commit_with_message(message: &str) -> Result<ExitStatus> {
 
     Ok(status)
 }
+
+pub fn init() -> Result<ExitStatus> {
+    let status = Command::new("git")
+        .arg("init")
+        .stdout(io::stderr())
+        .status()?;
+
+    if status.success() {
+        info!("{} Initialized git repository", "✓".green());
+    } else {
+        error!("Failed to initialize git repository");
+    }
+
+    Ok(status)
+}
+
+/// Whether the repository in the current directory has a commit to push.
+pub fn has_commits() -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .stdout(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub fn has_staged_changes() -> bool {
+    Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .status()
+        .map(|status| !status.success())
+        .unwrap_or(false)
+}
+
+pub fn has_remote(name: &str) -> bool {
+    Command::new("git")
+        .args(["remote", "get-url", name])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub fn add_remote(name: &str, url: &str) -> Result<ExitStatus> {
+    let status = Command::new("git")
+        .args(["remote", "add", name, url])
+        .stdout(io::stderr())
+        .status()?;
+
+    if status.success() {
+        info!("{} Remote {} added: {}", "✓".green(), name, url.bright_black());
+    } else {
+        error!("Failed to add remote {}", name);
+    }
+
+    Ok(status)
+}
+
+/// Merges `branch` from `remote` into the current branch even though the two
+/// share no history, as with a repository GitHub created with a first commit.
+pub fn pull_unrelated(remote: &str, branch: &str) -> Result<ExitStatus> {
+    let status = Command::new("git")
+        .args(["pull", "--no-rebase", "--no-edit", "--allow-unrelated-histories", remote, branch])
+        .stdout(io::stderr())
+        .status()?;
+
+    if !status.success() {
+        error!("Failed to merge {}/{}", remote, branch);
+    }
+
+    Ok(status)
+}
+
+/// Pushes the current branch to `branch` on `remote` and tracks it.
+pub fn push_head(remote: &str, branch: &str) -> Result<ExitStatus> {
+    let status = Command::new("git")
+        .args(["push", "--set-upstream", remote, &format!("HEAD:{}", branch)])
+        .stdout(io::stderr())
+        .status()?;
+
+    if status.success() {
+        info!("{} Pushed to {}/{}", "✓".green(), remote, branch);
+    } else {
+        error!("Failed to push to {}", remote);
+    }
+
+    Ok(status)
+}
+
+pub fn current_branch() -> Option<String> {
+    output(&["symbolic-ref", "--short", "HEAD"])
+}
+
+pub fn set_remote_url(name: &str, url: &str) -> Result<ExitStatus> {
+    let status = Command::new("git")
+        .args(["remote", "set-url", name, url])
+        .stdout(io::stderr())
+        .status()?;
+
+    if status.success() {
+        info!("{} Remote {} now points to {}", "✓".green(), name, url.bright_black());
+    } else {
+        error!("Failed to update remote {}", name);
+    }
+
+    Ok(status)
+}
+
+pub fn remote_url(name: &str) -> Option<String> {
+    output(&["remote", "get-url", name])
+}
+
+/// Path of the current directory relative to the top of the work tree,
+/// empty at the top itself.
+pub fn path_prefix() -> Option<String> {
+    output(&["rev-parse", "--show-prefix"])
+}
+
+pub fn head_commit() -> Option<String> {
+    output(&["rev-parse", "HEAD"])
+}
+
+/// The most recent tag reachable from HEAD, other than `exclude`.
+pub fn latest_tag(exclude: &str) -> Option<String> {
+    output(&["describe", "--tags", "--abbrev=0", "--exclude", exclude, "HEAD"])
+}
+
+/// One line per commit in `range`, oldest first, without merges.
+pub fn commit_subjects(range: &str) -> Vec<String> {
+    output(&["log", "--no-merges", "--reverse", "--format=%s", range])
+        .map(|log| log.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// `git status` in short form with the branch line, `None` outside a
+/// repository.
+pub fn status_short() -> Option<String> {
+    output(&["status", "--short", "--branch"])
+}
+
+/// Unified diff of `range` (e.g. `main..HEAD`), or of the staged changes.
+pub fn diff(range: Option<&str>) -> Result<String> {
+    run(&["diff", "--no-color", "--no-ext-diff", range.unwrap_or("--cached")])
+}
+
+pub fn is_commit(rev: &str) -> bool {
+    output(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)]).is_some()
+}
+
+/// The message and patch of a commit.
+pub fn show(rev: &str) -> Result<String> {
+    run(&["show", "--no-color", "--no-ext-diff", "--stat", "--patch", "--format=fuller", rev])
+}
+
+/// Full messages of the commits in `range`, newest first.
+pub fn commit_messages(range: &str) -> Result<String> {
+    run(&["log", "--no-color", "--format=%h %an %ad%n%B", "--date=short", range])
+}
+
+/// One line per commit that touched `path`, following renames.
+pub fn file_history(path: &str, limit: usize) -> Result<String> {
+    run(&["log", "--no-color", "--follow", &format!("-n{}", limit), "--format=%h %ad %an %s", "--date=short", "--", path])
+}
+
+/// `git blame` of `path`, or of lines `start` to `end` of it.
+pub fn blame(path: &str, lines: Option<(u32, u32)>) -> Result<String> {
+    match lines {
+        Some((start, end)) => run(&["blame", "--date=short", "-L", &format!("{},{}", start, end), "--", path]),
+        None => run(&["blame", "--date=short", "--", path]),
+    }
+}
+
+/// Output of a git command that is expected to succeed, with git's own
+/// message as the error otherwise.
+fn run(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn output(args: &[&str]) -> Option<String> {
+    Command::new("git")
+        .args(args)
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Local branch names of the repository in the current directory, empty
+/// outside a repository.
+pub fn local_branches() -> Vec<String> {
+    Command::new("git")
+        .args(["for-each-ref", "--format=%(refname:short)", "refs/heads"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Asks the configured git credential helper for a password for `host`
+/// without ever prompting the user.
+pub fn credential_fill(host: &str) -> Result<Option<String>> {
+    let mut child = Command::new("git")
+        .args(["credential", "fill"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(format!("protocol=https\nhost={}\n\n", host).as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(parse_credential_password(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_credential_password(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("password="))
+        .map(str::to_string)
+        .filter(|password| !password.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_credential_password_reads_password_field() {
+        let output = "protocol=https\nhost=github.com\nusername=octocat\npassword=ghp_secret\n";
+        assert_eq!(parse_credential_password(output), Some("ghp_secret".to_string()));
+        assert_eq!(parse_credential_password("protocol=https\nhost=github.com\n"), None);
+    }
+}
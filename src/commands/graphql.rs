@@ -0,0 +1,432 @@
+//! Client for GitHub's GraphQL API.
+//!
+//! Each query is a type implementing [`Query`], pairing the query text with
+//! typed variables and response data. Lookups that need several REST calls,
+//! such as a repository together with the signed-in user, fit in a single
+//! request here.
+
+use anyhow::Result;
+use log::{debug, warn};
+use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::auth;
+use crate::cache::SendCached;
+use crate::commands::github::{api_request, ApiError, GetRepoResponse};
+use crate::config;
+use crate::http;
+
+// Warn once fewer than this share of the hourly point budget is left.
+const LOW_RATE_LIMIT_RATIO: f64 = 0.1;
+
+pub trait Query {
+    const QUERY: &'static str;
+    type Variables: Serialize;
+    type Data: DeserializeOwned;
+}
+
+/// A query returning one page of a connection, continued by passing the
+/// previous page's end cursor back in as a variable.
+pub trait PagedQuery: Query {
+    type Node: DeserializeOwned;
+
+    fn connection(data: Self::Data) -> Connection<Self::Node>;
+    fn set_cursor(variables: &mut Self::Variables, cursor: String);
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Connection<T> {
+    pub nodes: Vec<T>,
+    #[serde(rename = "pageInfo")]
+    pub page_info: PageInfo,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RateLimit {
+    cost: u32,
+    remaining: u32,
+    limit: u32,
+    reset_at: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct GraphqlError {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct GraphqlResponse {
+    data: Option<serde_json::Value>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Serialize)]
+struct GraphqlRequest<'a, V> {
+    query: &'a str,
+    variables: &'a V,
+}
+
+/// GitHub Enterprise Server serves REST under `/api/v3` and GraphQL under
+/// `/api/graphql`; github.com has both at the root of `api.github.com`.
+pub(crate) fn endpoint() -> String {
+    let api = config::endpoints().github_api.trim_end_matches('/');
+    format!("{}/graphql", api.strip_suffix("/v3").unwrap_or(api))
+}
+
+/// Runs a query, answered from the on-disk cache like REST GETs are. All the
+/// queries here only read, which is what makes that safe.
+pub fn execute<Q: Query>(token: &str, variables: &Q::Variables) -> Result<Q::Data> {
+    let response = api_request(http::get_client(), Method::POST, token, &endpoint())
+        .json(&GraphqlRequest { query: Q::QUERY, variables })
+        .send_cached_query()?;
+
+    debug!("Status: {}", response.status());
+    if !response.status().is_success() {
+        return Err(ApiError { status: response.status(), context: "graphql request failed" }.into());
+    }
+
+    let response_text = response.text();
+    debug!("Response body: {}", response_text);
+    let response: GraphqlResponse = serde_json::from_str(&response_text)?;
+    if response.errors.iter().any(|e| e.kind.as_deref() == Some("NOT_FOUND")) {
+        debug!("GraphQL errors: {:?}", response.errors);
+        return Err(ApiError { status: StatusCode::NOT_FOUND, context: "graphql query found nothing" }.into());
+    }
+
+    let Some(data) = response.data.filter(|data| !data.is_null()) else {
+        return Err(query_failed(&response.errors));
+    };
+    if let Some(rate_limit) = data.get("rateLimit").and_then(|value| RateLimit::deserialize(value).ok()) {
+        report(&rate_limit);
+    }
+
+    // Errors alongside data mean only some fields could not be resolved;
+    // whether that matters depends on which ones the query needs.
+    match serde_json::from_value(data) {
+        Ok(data) => {
+            if !response.errors.is_empty() {
+                debug!("GraphQL returned partial data: {:?}", response.errors);
+            }
+            Ok(data)
+        }
+        Err(e) if response.errors.is_empty() => Err(e.into()),
+        Err(_) => Err(query_failed(&response.errors)),
+    }
+}
+
+/// Follows the connection until it ends or `limit` nodes have been collected.
+pub fn fetch_all<Q: PagedQuery>(token: &str, mut variables: Q::Variables, limit: usize) -> Result<Vec<Q::Node>> {
+    let mut nodes = Vec::new();
+    loop {
+        let page = Q::connection(execute::<Q>(token, &variables)?);
+        nodes.extend(page.nodes);
+
+        match page.page_info.end_cursor {
+            Some(cursor) if page.page_info.has_next_page && nodes.len() < limit => {
+                Q::set_cursor(&mut variables, cursor);
+            }
+            _ => break,
+        }
+    }
+    nodes.truncate(limit);
+    Ok(nodes)
+}
+
+fn query_failed(errors: &[GraphqlError]) -> anyhow::Error {
+    let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+    anyhow::anyhow!("GraphQL query failed: {}", messages.join("; "))
+}
+
+fn report(rate_limit: &RateLimit) {
+    debug!(
+        "GraphQL query cost {} point(s), {}/{} remaining until {}",
+        rate_limit.cost, rate_limit.remaining, rate_limit.limit, rate_limit.reset_at
+    );
+    if (rate_limit.remaining as f64) < rate_limit.limit as f64 * LOW_RATE_LIMIT_RATIO {
+        warn!(
+            "GitHub GraphQL rate limit is running low: {} of {} points left until {}",
+            rate_limit.remaining, rate_limit.limit, rate_limit.reset_at
+        );
+    }
+}
+
+#[derive(Serialize)]
+pub struct RepoVariables {
+    pub owner: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Viewer {
+    pub login: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepoDetails {
+    pub repository: GetRepoResponse,
+    /// Missing for GitHub App installation tokens, which don't act as a user.
+    pub viewer: Option<Viewer>,
+}
+
+/// A repository along with the signed-in user, which is what cloning needs
+/// to decide whether a fork's parent becomes `upstream`.
+pub struct RepoDetailsQuery;
+
+impl Query for RepoDetailsQuery {
+    const QUERY: &'static str = r#"
+        query($owner: String!, $name: String!) {
+          repository(owner: $owner, name: $name) {
+            fork: isFork
+            ssh_url: sshUrl
+            parent { ssh_url: sshUrl }
+          }
+          viewer { login }
+          rateLimit { cost remaining limit resetAt }
+        }"#;
+    type Variables = RepoVariables;
+    type Data = RepoDetails;
+}
+
+pub fn get_repo_details(owner: &str, repo_name: &str) -> Result<RepoDetails> {
+    debug!("Fetching repo details for {}/{}", owner, repo_name);
+
+    let token = auth::get_github_token()?;
+    execute::<RepoDetailsQuery>(&token, &RepoVariables { owner: owner.to_string(), name: repo_name.to_string() })
+}
+
+/// Everything `kit repo view` shows, with fields aliased to the names the
+/// REST API uses.
+pub struct RepoViewQuery;
+
+impl Query for RepoViewQuery {
+    const QUERY: &'static str = r#"
+        query($owner: String!, $name: String!) {
+          repository(owner: $owner, name: $name) {
+            full_name: nameWithOwner
+            html_url: url
+            description
+            fork: isFork
+            ssh_url: sshUrl
+            parent { ssh_url: sshUrl full_name: nameWithOwner }
+            stargazers_count: stargazerCount
+            default_branch: defaultBranchRef { name }
+            language: primaryLanguage { name }
+            topics: repositoryTopics(first: 20) { nodes { topic { name } } }
+            open_issues: issues(states: OPEN) { totalCount }
+            open_pull_requests: pullRequests(states: OPEN) { totalCount }
+          }
+          rateLimit { cost remaining limit resetAt }
+        }"#;
+    type Variables = RepoVariables;
+    type Data = RepoViewData;
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepoViewData {
+    pub repository: RepoView,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RepoView {
+    pub full_name: String,
+    pub html_url: String,
+    pub description: Option<String>,
+    pub fork: bool,
+    pub ssh_url: String,
+    pub parent: Option<RepoViewParent>,
+    pub stargazers_count: u64,
+    #[serde(deserialize_with = "name")]
+    pub default_branch: Option<String>,
+    #[serde(deserialize_with = "name")]
+    pub language: Option<String>,
+    #[serde(deserialize_with = "topic_names")]
+    pub topics: Vec<String>,
+    #[serde(deserialize_with = "total_count")]
+    pub open_issues: u64,
+    #[serde(deserialize_with = "total_count")]
+    pub open_pull_requests: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RepoViewParent {
+    pub full_name: String,
+    pub ssh_url: String,
+}
+
+pub fn get_repo_view(owner: &str, repo_name: &str) -> Result<RepoView> {
+    debug!("Fetching repo overview for {}/{}", owner, repo_name);
+
+    let token = auth::get_github_token()?;
+    let variables = RepoVariables { owner: owner.to_string(), name: repo_name.to_string() };
+    Ok(execute::<RepoViewQuery>(&token, &variables)?.repository)
+}
+
+#[derive(Serialize, Default)]
+pub struct ViewerReposVariables {
+    pub after: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepoNode {
+    #[serde(rename = "nameWithOwner")]
+    pub name_with_owner: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ViewerRepos {
+    viewer: ViewerRepositories,
+}
+
+#[derive(Deserialize, Debug)]
+struct ViewerRepositories {
+    repositories: Connection<RepoNode>,
+}
+
+/// Repositories the signed-in user owns, collaborates on or can see through
+/// an organization, most recently pushed first.
+pub struct ViewerReposQuery;
+
+impl Query for ViewerReposQuery {
+    const QUERY: &'static str = r#"
+        query($after: String) {
+          viewer {
+            repositories(
+              first: 100
+              after: $after
+              ownerAffiliations: [OWNER, COLLABORATOR, ORGANIZATION_MEMBER]
+              orderBy: { field: PUSHED_AT, direction: DESC }
+            ) {
+              nodes { nameWithOwner }
+              pageInfo { hasNextPage endCursor }
+            }
+          }
+          rateLimit { cost remaining limit resetAt }
+        }"#;
+    type Variables = ViewerReposVariables;
+    type Data = ViewerRepos;
+}
+
+impl PagedQuery for ViewerReposQuery {
+    type Node = RepoNode;
+
+    fn connection(data: ViewerRepos) -> Connection<RepoNode> {
+        data.viewer.repositories
+    }
+
+    fn set_cursor(variables: &mut ViewerReposVariables, cursor: String) {
+        variables.after = Some(cursor);
+    }
+}
+
+pub fn list_viewer_repos(token: &str, limit: usize) -> Result<Vec<String>> {
+    debug!("Listing repositories for the authenticated user");
+
+    let repos = fetch_all::<ViewerReposQuery>(token, ViewerReposVariables::default(), limit)?;
+    Ok(repos.into_iter().map(|repo| repo.name_with_owner).collect())
+}
+
+// GraphQL nests what REST returns as plain values; these flatten the shapes
+// the queries above produce so response types can stay flat.
+
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+
+/// `{ "name": ... }`, as returned for refs and languages, or null.
+fn name<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<String>, D::Error> {
+    Ok(Option::<Named>::deserialize(deserializer)?.map(|named| named.name))
+}
+
+/// `{ "totalCount": ... }` of a connection.
+fn total_count<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    struct Count {
+        #[serde(rename = "totalCount")]
+        total_count: u64,
+    }
+    Ok(Count::deserialize(deserializer)?.total_count)
+}
+
+/// `repositoryTopics { nodes { topic { name } } }`.
+fn topic_names<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    struct Topic {
+        topic: Named,
+    }
+    #[derive(Deserialize)]
+    struct Topics {
+        nodes: Vec<Topic>,
+    }
+    Ok(Topics::deserialize(deserializer)?.nodes.into_iter().map(|node| node.topic.name).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::github::is_not_found;
+    use crate::test_support;
+
+    // Every query goes to the same endpoint, so the fixtures for all of them
+    // are replayed from one route in the order the queries run.
+    #[test]
+    fn queries_parse_data_errors_and_pages() {
+        let server = test_support::server();
+        server.route(
+            "POST",
+            "/api/graphql",
+            &[
+                "github/graphql_repo_fork",
+                "github/graphql_repo_not_found",
+                "github/graphql_repo_view",
+                "github/graphql_viewer_repos_page1",
+                "github/graphql_viewer_repos_page2",
+            ],
+            Some("https://api.github.com/graphql"),
+        );
+
+        let details = get_repo_details("octocat", "kit").unwrap();
+        assert!(details.repository.fork);
+        assert_eq!(details.repository.ssh_url, "git@github.com:octocat/kit.git");
+        assert_eq!(details.repository.parent.unwrap().ssh_url, "git@github.com:kcterala/kit.git");
+        assert_eq!(details.viewer.unwrap().login, "octocat");
+        get_repo_details("octocat", "kit").unwrap();
+        assert_eq!(server.requests_to("/api/graphql").len(), 1);
+
+        let err = get_repo_details("octocat", "missing").unwrap_err();
+        assert!(is_not_found(&err));
+
+        let view = get_repo_view("octocat", "Hello-World").unwrap();
+        assert_eq!(view.description.as_deref(), Some("My first repository on GitHub!"));
+        assert_eq!(view.default_branch.as_deref(), Some("master"));
+        assert_eq!(view.language, None);
+        assert_eq!(view.topics, vec!["git", "example"]);
+        assert_eq!((view.stargazers_count, view.open_issues, view.open_pull_requests), (2700, 1024, 389));
+
+        let repos = list_viewer_repos("test-token", 500).unwrap();
+        assert_eq!(repos, vec!["octocat/kit", "octocat/Hello-World", "kcterala/kit"]);
+
+        let requests = server.requests_to("/api/graphql");
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer test-token"));
+        let variables = |index: usize| {
+            let body: serde_json::Value = serde_json::from_str(&requests[index].body).unwrap();
+            body["variables"].clone()
+        };
+        assert_eq!(variables(0)["owner"], "octocat");
+        assert_eq!(variables(3)["after"], serde_json::Value::Null);
+        assert_eq!(variables(4)["after"], "Y3Vyc29yOnYyOpK5");
+    }
+}
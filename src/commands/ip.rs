@@ -0,0 +1,215 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use anyhow::Result;
+use log::{debug, warn};
+use reqwest::blocking::Client;
+use serde::Serialize;
+
+use crate::config;
+use crate::http;
+
+const IPIFY_V4_URL: &str = "https://api.ipify.org";
+const IPIFY_V6_URL: &str = "https://api6.ipify.org";
+const ICANHAZIP_V4_URL: &str = "https://ipv4.icanhazip.com";
+const ICANHAZIP_V6_URL: &str = "https://ipv6.icanhazip.com";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AddressFamily {
+    V4,
+    V6,
+}
+
+impl AddressFamily {
+    // Binding to the unspecified address of a family forces the request
+    // out over that family, whatever the provider's DNS returns.
+    fn unspecified(self) -> IpAddr {
+        match self {
+            AddressFamily::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            AddressFamily::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    fn matches(self, ip: &IpAddr) -> bool {
+        match self {
+            AddressFamily::V4 => ip.is_ipv4(),
+            AddressFamily::V6 => ip.is_ipv6(),
+        }
+    }
+}
+
+#[derive(Serialize, Default, Debug)]
+pub struct IpInfo {
+    pub ip: String,
+    pub provider: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warp: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LocalAddress {
+    pub interface: String,
+    pub ip: IpAddr,
+}
+
+enum Provider {
+    CloudflareTrace(String),
+    PlainText(&'static str, String),
+}
+
+fn providers(family: AddressFamily) -> Vec<Provider> {
+    let endpoints = config::endpoints();
+    let trace_url = match family {
+        AddressFamily::V4 => endpoints.ip_trace.clone(),
+        AddressFamily::V6 => endpoints.ip_trace_v6.clone(),
+    };
+
+    let mut providers = vec![Provider::CloudflareTrace(trace_url)];
+    if let Ok(Some(url)) = config::load_ip_fallback_url() {
+        providers.push(Provider::PlainText("custom", url));
+    }
+    match family {
+        AddressFamily::V4 => {
+            providers.push(Provider::PlainText("ipify", IPIFY_V4_URL.to_string()));
+            providers.push(Provider::PlainText("icanhazip", ICANHAZIP_V4_URL.to_string()));
+        }
+        AddressFamily::V6 => {
+            providers.push(Provider::PlainText("ipify", IPIFY_V6_URL.to_string()));
+            providers.push(Provider::PlainText("icanhazip", ICANHAZIP_V6_URL.to_string()));
+        }
+    }
+    providers
+}
+
+/// Looks up the public address for `family`, moving on to the next provider
+/// whenever one is unreachable or returns something unexpected.
+pub fn fetch_public_ip(family: AddressFamily) -> Result<IpInfo> {
+    let client = http::get_client_bound_to(family.unspecified())?;
+    let mut last_error = None;
+
+    for provider in providers(family) {
+        let result = match &provider {
+            Provider::CloudflareTrace(url) => fetch_trace(&client, url),
+            Provider::PlainText(name, url) => fetch_plain_text(&client, name, url),
+        };
+
+        match result {
+            Ok(info) if info.ip.parse::<IpAddr>().is_ok_and(|ip| family.matches(&ip)) => return Ok(info),
+            Ok(info) => debug!("{} returned {} for {:?}, trying next provider", info.provider, info.ip, family),
+            Err(e) => {
+                debug!("IP lookup failed: {}", e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No provider returned a {:?} address", family)))
+}
+
+fn fetch_trace(client: &Client, url: &str) -> Result<IpInfo> {
+    let response = client.get(url)
+        .header("User-Agent", "kit-cli")
+        .send()?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Cloudflare trace returned {}", response.status()));
+    }
+
+    parse_trace(&response.text()?)
+}
+
+fn parse_trace(body: &str) -> Result<IpInfo> {
+    let mut info = IpInfo {
+        provider: "cloudflare".to_string(),
+        ..Default::default()
+    };
+
+    for line in body.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let field = match key {
+            "ip" => {
+                info.ip = value.to_string();
+                continue;
+            }
+            "loc" => &mut info.location,
+            "colo" => &mut info.colo,
+            "http" => &mut info.http,
+            "tls" => &mut info.tls,
+            "warp" => &mut info.warp,
+            _ => continue,
+        };
+        *field = Some(value.to_string());
+    }
+
+    if info.ip.is_empty() {
+        return Err(anyhow::anyhow!("Could not parse IP address from response"));
+    }
+    Ok(info)
+}
+
+fn fetch_plain_text(client: &Client, name: &str, url: &str) -> Result<IpInfo> {
+    let response = client.get(url)
+        .header("User-Agent", "kit-cli")
+        .send()?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("{} returned {}", name, response.status()));
+    }
+
+    Ok(IpInfo {
+        ip: response.text()?.trim().to_string(),
+        provider: name.to_string(),
+        ..Default::default()
+    })
+}
+
+pub fn local_addresses() -> Vec<LocalAddress> {
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces
+            .into_iter()
+            .filter(|interface| !interface.is_loopback())
+            .map(|interface| LocalAddress {
+                ip: interface.ip(),
+                interface: interface.name,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to list network interfaces: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn fetch_public_ip_reads_trace_fields() {
+        let server = test_support::server();
+        server.route("GET", "/cdn-cgi/trace", &["trace/cdn_trace"], Some("https://1.1.1.1/cdn-cgi/trace"));
+
+        let info = fetch_public_ip(AddressFamily::V4).unwrap();
+        assert_eq!(info.ip, "203.0.113.7");
+        assert_eq!(info.provider, "cloudflare");
+        assert_eq!(info.location.as_deref(), Some("NL"));
+        assert_eq!(info.colo.as_deref(), Some("AMS"));
+        assert_eq!(info.tls.as_deref(), Some("TLSv1.3"));
+        assert_eq!(info.warp.as_deref(), Some("off"));
+    }
+
+    #[test]
+    fn parse_trace_requires_ip() {
+        assert!(parse_trace("loc=NL\ncolo=AMS\n").is_err());
+    }
+}
@@ -1,33 +1,87 @@
 use anyhow::{Result};
-use log::{info, error, warn};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use log::{debug, info, error, warn};
+use serde::Serialize;
 use colored::*;
+use inquire::Confirm;
+use spinoff::{spinners, Spinner, Color, Streams};
 
-use crate::commands::github::GetRepoResponse;
+use crate::commands::graphql::RepoDetails;
 use crate::utils;
-use crate::http;
-use crate::config;
 use crate::auth;
+use crate::config;
+use crate::cache;
+use crate::output::{self, OutputFormat};
 
 pub mod github;
-mod git;
+pub mod graphql;
+mod actions;
+mod ask;
+mod explain;
+mod gist;
+pub mod git;
 mod ai;
+mod ip;
+mod net;
+mod release;
+mod review;
+mod update;
+
+pub use ai::SuggestionOverrides;
+
+const NET_TIMEOUT: Duration = Duration::from_secs(5);
+const GITHUB_SSH_HOST: &str = "github.com";
+// GitHub's fallback for networks that block port 22.
+const GITHUB_SSH_OVER_HTTPS_HOST: &str = "ssh.github.com";
+
+const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Public repositories need no scope at all, only a token.
+const CLONE_SCOPES: &[&str] = &[];
+// Private repositories are reported as 404s until the token can see them.
+const PRIVATE_REPO_SCOPES: &[&str] = &["repo"];
+const WRITE_REPO_SCOPES: &[&str] = &["public_repo"];
+const DELETE_REPO_SCOPES: &[&str] = &["delete_repo"];
+const INITIAL_COMMIT_MESSAGE: &str = "Initial commit";
+const GITIGNORE_FILE: &str = ".gitignore";
+const GIST_SCOPES: &[&str] = &["gist"];
+const CLIPBOARD_GIST_FILENAME: &str = "clipboard.txt";
 
-const BASE_URL_FOR_IP: &str = "https://1.1.1.1/cdn-cgi/trace";
+#[derive(Serialize)]
+pub struct CommitResult {
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct CloneResult {
+    pub repository: String,
+    pub ssh_url: String,
+    pub fork: bool,
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+}
 
-pub fn commit(message: &str) -> Result<()> {
+pub fn commit(message: &str, overrides: &SuggestionOverrides) -> Result<CommitResult> {
     if !git::is_git_repo() {
         return Err(anyhow::anyhow!("Not a git repository"));
     }
 
-    let polished_message = ai::get_polished_commit_msg(message)?;
+    let polished_message = ai::get_polished_commit_msg(message, overrides)?;
 
     git::add_all()?;
-    git::commit_with_message(&polished_message)?;
+    let status = git::commit_with_message(&polished_message)?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("git commit failed"));
+    }
 
-    Ok(())
+    Ok(CommitResult { message: polished_message })
 }
 
-pub fn clone_repository(repo: &str) -> Result<()> {
+pub fn clone_repository(repo: &str, branch: Option<&str>) -> Result<CloneResult> {
     let (owner, repo_name) = match resolve(repo) {
         Some(result) => result,
         None => {
@@ -37,33 +91,79 @@ pub fn clone_repository(repo: &str) -> Result<()> {
     };
 
     // Ensure we have credentials (will trigger login if needed)
-    auth::get_github_token()?;
+    auth::ensure_scopes(CLONE_SCOPES)?;
 
     info!("Cloning repository {}/{}", owner, repo_name);
-    let repo_details: GetRepoResponse = github::get_repo_details(&owner, &repo_name)?;
-    let clone_status = git::clone_repository(&repo_details)?;
+    let details = get_repo_details_with_private_access(&owner, &repo_name)?;
+    let repo_details = &details.repository;
+    let clone_status = git::clone_repository(repo_details, branch)?;
 
     if !clone_status.success() {
         return Err(anyhow::anyhow!("Could not clone repository"));
     }
 
     // Only add upstream if it's a fork AND owner matches logged-in user
-    if should_add_upstream(&owner, &repo_details)? {
+    let mut upstream = None;
+    if should_add_upstream(&owner, &details)? {
         info!("Repository is a fork, adding parent as upstream remote");
         let parent = repo_details.parent
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Forked repository has no parent"))?;
 
-        git::add_upstream(&repo_name, &parent.ssh_url)?;
+        if git::add_upstream(&repo_name, &parent.ssh_url)?.success() {
+            upstream = Some(parent.ssh_url.clone());
+        }
     }
 
-    Ok(())
+    Ok(CloneResult {
+        repository: format!("{}/{}", owner, repo_name),
+        ssh_url: repo_details.ssh_url.clone(),
+        fork: repo_details.fork,
+        branch: branch.map(str::to_string),
+        upstream,
+    })
 }
 
 
-fn should_add_upstream(owner: &str, repo_details: &GetRepoResponse) -> Result<bool> {
-    let github_username = config::load_username()?;
-    Ok(repo_details.fork && owner.eq_ignore_ascii_case(&github_username))
+fn get_repo_details_with_private_access(owner: &str, repo_name: &str) -> Result<RepoDetails> {
+    with_private_access(|| graphql::get_repo_details(owner, repo_name))
+        .map_err(|e| not_accessible(e, owner, repo_name))
+}
+
+/// Runs `request` and, when GitHub answers 404 as it does for private
+/// repositories the token can't see, offers to add the `repo` scope and runs
+/// it again with the new token. `request` must look the token up itself.
+fn with_private_access<T>(mut request: impl FnMut() -> Result<T>) -> Result<T> {
+    match request() {
+        Err(e) if github::is_not_found(&e) && auth::ensure_scopes(PRIVATE_REPO_SCOPES)? => request(),
+        result => result,
+    }
+}
+
+fn not_accessible(err: anyhow::Error, owner: &str, repo_name: &str) -> anyhow::Error {
+    if github::is_not_found(&err) {
+        return anyhow::anyhow!("Repository {}/{} not found or not accessible", owner, repo_name);
+    }
+    err
+}
+
+fn should_add_upstream(owner: &str, details: &RepoDetails) -> Result<bool> {
+    if !details.repository.fork {
+        return Ok(false);
+    }
+
+    // The lookup usually names the signed-in user already, saving a request.
+    if let Some(viewer) = &details.viewer {
+        return Ok(owner.eq_ignore_ascii_case(&viewer.login));
+    }
+
+    match auth::get_github_username() {
+        Ok(github_username) => Ok(owner.eq_ignore_ascii_case(&github_username)),
+        Err(e) => {
+            warn!("Could not determine GitHub username, skipping upstream: {}", e);
+            Ok(false)
+        }
+    }
 }
 
 pub fn fork_repository(repo: &str) -> Result<()> {
@@ -72,57 +172,1633 @@ pub fn fork_repository(repo: &str) -> Result<()> {
 }
 
 
-pub fn ip(copy_to_clipboard: bool) -> Result<()> {
-    let client = http::get_client();
+pub struct RepoCreateOptions {
+    pub name: Option<String>,
+    pub org: Option<String>,
+    pub private: bool,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub gitignore: Option<String>,
+}
 
-    let response = client.get(BASE_URL_FOR_IP)
-        .header("User-Agent", "kit-cli")
-        .send()?;
+#[derive(Serialize)]
+pub struct RepoCreateResult {
+    pub repository: String,
+    pub html_url: String,
+    pub ssh_url: String,
+    pub private: bool,
+    pub initialized: bool,
+    pub pushed: bool,
+}
 
-    if !response.status().is_success() {
-        error!("Failed to fetch IP address from Cloudflare");
-        return Err(anyhow::anyhow!("Failed to fetch IP address"));
+/// Creates a GitHub repository for the current directory, turning it into a
+/// git repository first if needed, and pushes it as `origin`.
+pub fn repo_create(options: &RepoCreateOptions) -> Result<RepoCreateResult> {
+    let name = match &options.name {
+        Some(name) => name.clone(),
+        None => std::env::current_dir()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow::anyhow!("Could not determine a repository name, pass one explicitly"))?,
+    };
+
+    let initialized = !git::is_git_repo();
+    if !initialized && git::has_remote("origin") {
+        return Err(anyhow::anyhow!("This repository already has an origin remote"));
     }
 
-    let body = response.text()?;
+    auth::ensure_scopes(if options.private { PRIVATE_REPO_SCOPES } else { WRITE_REPO_SCOPES })?;
+    let token = auth::get_github_token()?;
 
-    // Parse the response to extract information
-    let mut ip_address = None;
-    let mut location = None;
+    if initialized && !git::init()?.success() {
+        return Err(anyhow::anyhow!("Could not initialize git repository"));
+    }
 
-    for line in body.lines() {
-        if let Some(value) = line.strip_prefix("ip=") {
-            ip_address = Some(value.to_string());
-        } else if let Some(value) = line.strip_prefix("loc=") {
-            location = Some(value.to_string());
+    // Nothing is committed yet, so the template has to be in place before
+    // the first commit or it would include what the template ignores.
+    let mut gitignore_template = options.gitignore.clone();
+    if !git::has_commits() {
+        if let Some(template) = gitignore_template.take() {
+            write_gitignore(&token, &template)?;
+        }
+        if !git::add_all()?.success() {
+            return Err(anyhow::anyhow!("Could not stage files for the initial commit"));
+        }
+        if git::has_staged_changes() && !git::commit_with_message(INITIAL_COMMIT_MESSAGE)?.success() {
+            return Err(anyhow::anyhow!("Could not create the initial commit"));
         }
     }
 
-    if let Some(ip) = ip_address {
-        info!("Your IP address: {}", ip.cyan().bold());
+    let request = github::CreateRepoRequest {
+        name,
+        description: options.description.clone(),
+        private: options.private,
+        license_template: options.license.clone(),
+        gitignore_template,
+    };
+    let repo = github::create_repository(&token, options.org.as_deref(), &request)?;
+    info!("{} Created {} ({})", "✓".green(), repo.full_name.cyan().bold(), repo.html_url.bright_black());
 
-        if let Some(loc) = location {
-            info!("Location: {}", loc.yellow());
+    // From here on the repository exists on GitHub whatever fails locally.
+    let created = |problem: &str| {
+        anyhow::anyhow!(
+            "{}. {} was created on GitHub anyway; to finish, run `git remote add origin {}` if needed and push",
+            problem,
+            repo.full_name,
+            repo.ssh_url
+        )
+    };
+
+    let mut result = RepoCreateResult {
+        repository: repo.full_name.clone(),
+        html_url: repo.html_url.clone(),
+        ssh_url: repo.ssh_url.clone(),
+        private: repo.private,
+        initialized,
+        pushed: false,
+    };
+
+    if !git::add_remote("origin", &result.ssh_url).is_ok_and(|status| status.success()) {
+        return Err(created("Could not add origin remote"));
+    }
+    if !git::has_commits() {
+        warn!("Nothing committed yet, skipping push");
+        return Ok(result);
+    }
+
+    // Templates make GitHub create a first commit of its own, which has to be
+    // merged before anything can be pushed on top of it.
+    let templated = request.license_template.is_some() || request.gitignore_template.is_some();
+    let branch = if templated {
+        if !git::pull_unrelated("origin", &repo.default_branch).is_ok_and(|status| status.success()) {
+            return Err(created("Could not merge the files GitHub created, resolve the merge"));
+        }
+        repo.default_branch.clone()
+    } else {
+        git::current_branch().unwrap_or_else(|| repo.default_branch.clone())
+    };
+
+    result.pushed = git::push_head("origin", &branch).is_ok_and(|status| status.success());
+    if !result.pushed {
+        return Err(created(&format!("Could not push to {}", result.ssh_url)));
+    }
+    Ok(result)
+}
+
+/// Adds GitHub's `.gitignore` template called `name` to the `.gitignore` in
+/// the current directory, creating it if needed.
+fn write_gitignore(token: &str, name: &str) -> Result<()> {
+    let template = match github::get_gitignore_template(token, name) {
+        Err(e) if github::is_not_found(&e) => return Err(anyhow::anyhow!("GitHub has no .gitignore template called {}", name)),
+        result => result?,
+    };
+
+    let mut contents = fs::read_to_string(GITIGNORE_FILE).unwrap_or_default();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(&template.source);
+    fs::write(GITIGNORE_FILE, contents)?;
+    info!("{} Added the {} .gitignore template", "✓".green(), name);
+    Ok(())
+}
+
+/// Shows an overview of `repo`, or of the repository `origin` points to.
+pub fn repo_view(repo: Option<&str>) -> Result<graphql::RepoView> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+
+    let view = with_private_access(|| graphql::get_repo_view(&owner, &repo_name))
+        .map_err(|e| not_accessible(e, &owner, &repo_name))?;
+
+    info!("{}", view.full_name.cyan().bold());
+    if let Some(description) = &view.description {
+        info!("{}", description);
+    }
+    if let Some(parent) = &view.parent {
+        info!("Forked from {}", parent.full_name);
+    }
+    info!("");
+    info!("Stars:          {}", view.stargazers_count);
+    info!("Default branch: {}", view.default_branch.as_deref().unwrap_or("-"));
+    info!("Language:       {}", view.language.as_deref().unwrap_or("-"));
+    if !view.topics.is_empty() {
+        info!("Topics:         {}", view.topics.join(", "));
+    }
+    info!("Open issues:    {}", view.open_issues);
+    info!("Open PRs:       {}", view.open_pull_requests);
+    info!("{}", view.html_url.bright_black());
+
+    Ok(view)
+}
+
+/// Asks before doing something that can't easily be undone. `yes` answers
+/// for scripts, which have no terminal to be asked on.
+fn confirm(prompt: &str, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("Confirmation needed but stdin is not a terminal, pass --yes to proceed"));
+    }
+
+    if !Confirm::new(prompt).with_default(false).prompt()? {
+        return Err(anyhow::anyhow!("Cancelled"));
+    }
+    Ok(())
+}
+
+pub struct RepoEditOptions {
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub private: Option<bool>,
+    pub default_branch: Option<String>,
+    pub add_topics: Vec<String>,
+    pub remove_topics: Vec<String>,
+    pub yes: bool,
+}
+
+pub fn repo_edit(repo: Option<&str>, options: &RepoEditOptions) -> Result<github::RepoInfo> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let request = github::EditRepoRequest {
+        description: options.description.clone(),
+        homepage: options.homepage.clone(),
+        private: options.private,
+        default_branch: options.default_branch.clone(),
+        ..Default::default()
+    };
+    let edits_topics = !options.add_topics.is_empty() || !options.remove_topics.is_empty();
+    if request.description.is_none()
+        && request.homepage.is_none()
+        && request.private.is_none()
+        && request.default_branch.is_none()
+        && !edits_topics
+    {
+        return Err(anyhow::anyhow!("Nothing to change, see kit repo edit --help"));
+    }
+
+    match options.private {
+        Some(false) => confirm(&format!("Make {}/{} public? Anyone will be able to see it.", owner, repo_name), options.yes)?,
+        Some(true) => confirm(&format!("Make {}/{} private? Stars and watchers from outside will be lost.", owner, repo_name), options.yes)?,
+        None => {}
+    }
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    // An empty edit still returns the repository, including its topics.
+    let mut repo = with_private_access(|| {
+        github::update_repository(&auth::get_github_token()?, &owner, &repo_name, &request)
+    })?;
+    if edits_topics {
+        let token = auth::get_github_token()?;
+        let topics = apply_topic_changes(&repo.topics, &options.add_topics, &options.remove_topics);
+        repo.topics = github::replace_topics(&token, &owner, &repo_name, &topics)?;
+    }
+
+    info!("{} Updated {}", "✓".green(), repo.full_name.cyan().bold());
+    if options.default_branch.is_some() {
+        info!("Default branch: {}", repo.default_branch);
+    }
+    if edits_topics {
+        info!("Topics: {}", if repo.topics.is_empty() { "-".to_string() } else { repo.topics.join(", ") });
+    }
+    Ok(repo)
+}
+
+/// GitHub only accepts lowercase topics, so names are compared that way.
+fn apply_topic_changes(current: &[String], add: &[String], remove: &[String]) -> Vec<String> {
+    let remove: Vec<String> = remove.iter().map(|topic| topic.to_lowercase()).collect();
+    let mut topics: Vec<String> = current.iter().filter(|topic| !remove.contains(topic)).cloned().collect();
+    for topic in add.iter().map(|topic| topic.to_lowercase()) {
+        if !topics.contains(&topic) {
+            topics.push(topic);
+        }
+    }
+    topics
+}
+
+pub fn repo_archive(repo: Option<&str>, yes: bool) -> Result<github::RepoInfo> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    confirm(&format!("Archive {}/{}? It will become read-only.", owner, repo_name), yes)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let request = github::EditRepoRequest { archived: Some(true), ..Default::default() };
+    let repo = with_private_access(|| {
+        github::update_repository(&auth::get_github_token()?, &owner, &repo_name, &request)
+    })?;
+
+    info!("{} Archived {}", "✓".green(), repo.full_name.cyan().bold());
+    Ok(repo)
+}
+
+#[derive(Serialize)]
+pub struct RepoDeleteResult {
+    pub repository: String,
+    pub deleted: bool,
+}
+
+pub fn repo_delete(repo: Option<&str>, yes: bool) -> Result<RepoDeleteResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let repository = format!("{}/{}", owner, repo_name);
+    confirm(&format!("Permanently delete {}? This cannot be undone.", repository.bold()), yes)?;
+
+    auth::ensure_scopes(DELETE_REPO_SCOPES)?;
+    with_private_access(|| github::delete_repository(&auth::get_github_token()?, &owner, &repo_name))?;
+
+    info!("{} Deleted {}", "✓".green(), repository.cyan().bold());
+    Ok(RepoDeleteResult { repository, deleted: true })
+}
+
+/// Renames the repository, pointing `origin` at the new name (over the same
+/// protocol) when it is the repository being renamed.
+pub fn repo_rename(repo: Option<&str>, new_name: &str) -> Result<github::RepoInfo> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let request = github::EditRepoRequest { name: Some(new_name.to_string()), ..Default::default() };
+    let renamed = with_private_access(|| {
+        github::update_repository(&auth::get_github_token()?, &owner, &repo_name, &request)
+    })?;
+    info!("{} Renamed {}/{} to {}", "✓".green(), owner, repo_name, renamed.full_name.cyan().bold());
+
+    let renames_origin = |url: &String| resolve(url).is_some_and(|(o, r)| o == owner && r == repo_name);
+    if let Some(origin) = git::remote_url("origin").filter(renames_origin) {
+        let url = if origin.starts_with("https://") { &renamed.clone_url } else { &renamed.ssh_url };
+        git::set_remote_url("origin", url)?;
+    }
+    Ok(renamed)
+}
+
+pub fn repo_transfer(repo: Option<&str>, new_owner: &str, yes: bool) -> Result<github::RepoInfo> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    confirm(&format!("Transfer {}/{} to {}?", owner, repo_name, new_owner.bold()), yes)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let repo = with_private_access(|| {
+        github::transfer_repository(&auth::get_github_token()?, &owner, &repo_name, new_owner)
+    })?;
+
+    info!("{} Transfer of {}/{} to {} started", "✓".green(), owner, repo_name, new_owner.cyan().bold());
+    info!("Transfers to a user complete once they accept the invitation; update your remotes afterwards.");
+    Ok(repo)
+}
+
+pub struct ProtectOptions {
+    pub required_reviews: Option<u8>,
+    pub dismiss_stale_reviews: bool,
+    pub require_code_owner_reviews: bool,
+    pub status_checks: Vec<String>,
+    pub strict: bool,
+    pub enforce_admins: bool,
+    pub allow_force_pushes: bool,
+    pub allow_deletions: bool,
+    pub yes: bool,
+}
+
+#[derive(Serialize)]
+pub struct ProtectResult {
+    pub repository: String,
+    pub branch: String,
+    pub protected: bool,
+}
+
+/// Replaces the protection rules of `branch` with `options`, asking first
+/// when the branch already has some.
+pub fn repo_protect(repo: Option<&str>, branch: &str, options: &ProtectOptions) -> Result<ProtectResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let protection = github::BranchProtection {
+        required_status_checks: (!options.status_checks.is_empty()).then(|| github::RequiredStatusChecks {
+            strict: options.strict,
+            contexts: options.status_checks.clone(),
+        }),
+        enforce_admins: options.enforce_admins,
+        required_pull_request_reviews: options.required_reviews.map(|count| github::RequiredReviews {
+            required_approving_review_count: count,
+            dismiss_stale_reviews: options.dismiss_stale_reviews,
+            require_code_owner_reviews: options.require_code_owner_reviews,
+        }),
+        restrictions: None,
+        allow_force_pushes: options.allow_force_pushes,
+        allow_deletions: options.allow_deletions,
+    };
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let current = with_private_access(|| github::get_branch(&auth::get_github_token()?, &owner, &repo_name, branch))?;
+    if current.protected {
+        confirm(&format!("Replace the existing protection rules of {} of {}/{}?", branch.bold(), owner, repo_name), options.yes)?;
+    }
+    github::protect_branch(&auth::get_github_token()?, &owner, &repo_name, branch, &protection)?;
+
+    info!("{} Protected {} of {}/{}", "✓".green(), branch.cyan().bold(), owner, repo_name);
+    Ok(ProtectResult { repository: format!("{}/{}", owner, repo_name), branch: branch.to_string(), protected: true })
+}
+
+pub fn repo_unprotect(repo: Option<&str>, branch: &str, yes: bool) -> Result<ProtectResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    confirm(&format!("Remove all protection rules from {} of {}/{}?", branch.bold(), owner, repo_name), yes)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    with_private_access(|| github::unprotect_branch(&auth::get_github_token()?, &owner, &repo_name, branch))?;
+
+    info!("{} Removed protection from {} of {}/{}", "✓".green(), branch.cyan().bold(), owner, repo_name);
+    Ok(ProtectResult { repository: format!("{}/{}", owner, repo_name), branch: branch.to_string(), protected: false })
+}
+
+pub fn collaborator_list(repo: Option<&str>) -> Result<Vec<github::Collaborator>> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let collaborators = with_private_access(|| github::list_collaborators(&auth::get_github_token()?, &owner, &repo_name))?;
+
+    for collaborator in &collaborators {
+        info!("{:<24} {}", collaborator.login, collaborator.role_name.bright_black());
+    }
+    Ok(collaborators)
+}
+
+#[derive(Serialize)]
+pub struct CollaboratorResult {
+    pub repository: String,
+    pub login: String,
+    pub permission: Option<String>,
+    pub invited: bool,
+}
+
+pub fn collaborator_add(repo: Option<&str>, username: &str, permission: &str) -> Result<CollaboratorResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let invited = with_private_access(|| {
+        github::add_collaborator(&auth::get_github_token()?, &owner, &repo_name, username, permission)
+    })?;
+
+    if invited {
+        info!("{} Invited {} to {}/{} with {} access", "✓".green(), username.cyan().bold(), owner, repo_name, permission);
+    } else {
+        info!("{} {} now has {} access to {}/{}", "✓".green(), username.cyan().bold(), permission, owner, repo_name);
+    }
+    Ok(CollaboratorResult {
+        repository: format!("{}/{}", owner, repo_name),
+        login: username.to_string(),
+        permission: Some(permission.to_string()),
+        invited,
+    })
+}
+
+pub fn collaborator_remove(repo: Option<&str>, username: &str, yes: bool) -> Result<CollaboratorResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    confirm(&format!("Remove {} from {}/{}?", username.bold(), owner, repo_name), yes)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    with_private_access(|| github::remove_collaborator(&auth::get_github_token()?, &owner, &repo_name, username))?;
+
+    info!("{} Removed {} from {}/{}", "✓".green(), username.cyan().bold(), owner, repo_name);
+    Ok(CollaboratorResult {
+        repository: format!("{}/{}", owner, repo_name),
+        login: username.to_string(),
+        permission: None,
+        invited: false,
+    })
+}
+
+pub struct RunListOptions {
+    pub branch: Option<String>,
+    pub all_branches: bool,
+    pub workflow: Option<String>,
+    pub limit: u8,
+}
+
+/// Lists recent workflow runs, for the current branch unless told otherwise.
+pub fn run_list(repo: Option<&str>, options: &RunListOptions) -> Result<Vec<github::WorkflowRun>> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let branch = match &options.branch {
+        Some(branch) => Some(branch.clone()),
+        None if options.all_branches || repo.is_some() => None,
+        None => git::current_branch(),
+    };
+
+    let filter = github::RunFilter {
+        branch: branch.as_deref(),
+        workflow: options.workflow.as_deref(),
+        limit: options.limit,
+    };
+    let runs = with_private_access(|| github::list_workflow_runs(&auth::get_github_token()?, &owner, &repo_name, &filter))?;
+
+    if runs.is_empty() {
+        info!("No runs found{}", branch.map(|branch| format!(" on {}", branch)).unwrap_or_default());
+    }
+    for run in &runs {
+        info!(
+            "{} {:<11} {:<40} {:<12} {:<8} {}",
+            actions::symbol(run.status.as_deref(), run.conclusion.as_deref()),
+            run.id.to_string().cyan(),
+            truncate(&run.display_title, 40),
+            truncate(run.name.as_deref().unwrap_or("-"), 12),
+            run.event,
+            actions::short_time(&run.created_at).bright_black()
+        );
+    }
+    Ok(runs)
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(width - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[derive(Serialize)]
+pub struct RunView {
+    #[serde(flatten)]
+    pub run: github::WorkflowRun,
+    pub jobs: Vec<github::Job>,
+}
+
+impl RunView {
+    /// Fails unless the run completed successfully, for scripts waiting on it.
+    pub fn ensure_succeeded(&self) -> Result<()> {
+        match self.run.status.as_deref() {
+            Some("completed") if actions::succeeded(self.run.conclusion.as_deref()) => Ok(()),
+            Some("completed") => Err(output::Reported(format!(
+                "Run {} {}",
+                self.run.id,
+                self.run.conclusion.as_deref().unwrap_or("failed")
+            ))
+            .into()),
+            _ => Err(output::Reported(format!("Run {} has not completed", self.run.id)).into()),
+        }
+    }
+}
+
+/// Shows a run with its jobs, and the steps of jobs that did not succeed.
+pub fn run_view(repo: Option<&str>, run_id: u64) -> Result<RunView> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let run = with_private_access(|| github::get_workflow_run(&auth::get_github_token()?, &owner, &repo_name, run_id))?;
+    let jobs = github::list_run_jobs(&auth::get_github_token()?, &owner, &repo_name, run_id)?;
+
+    info!(
+        "{} {} {}",
+        actions::symbol(run.status.as_deref(), run.conclusion.as_deref()),
+        run.display_title.bold(),
+        format!("#{}", run.run_number).bright_black()
+    );
+    info!(
+        "{} on {} ({}) · {}",
+        run.name.as_deref().unwrap_or("Workflow"),
+        run.head_branch.as_deref().unwrap_or("-"),
+        &run.head_sha[..run.head_sha.len().min(7)],
+        actions::short_time(&run.created_at)
+    );
+    info!("");
+    for job in &jobs {
+        info!("{} {}", actions::symbol(Some(&job.status), job.conclusion.as_deref()), job.name);
+        if job.status == "completed" && actions::succeeded(job.conclusion.as_deref()) {
+            continue;
+        }
+        for step in job.steps.iter().filter(|step| !actions::succeeded(step.conclusion.as_deref())) {
+            info!("    {} {}", actions::symbol(Some(&step.status), step.conclusion.as_deref()), step.name);
+        }
+    }
+    info!("{}", run.html_url.bright_black());
+
+    Ok(RunView { run, jobs })
+}
+
+/// Watches `run_id`, or the latest run on the current branch, until it
+/// completes.
+pub fn run_watch(repo: Option<&str>, run_id: Option<u64>, interval: u64) -> Result<RunView> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let run_id = with_private_access(|| {
+        let token = auth::get_github_token()?;
+        match run_id {
+            Some(run_id) => github::get_workflow_run(&token, &owner, &repo_name, run_id).map(|run| run.id),
+            None => {
+                let branch = git::current_branch();
+                let filter = github::RunFilter { branch: branch.as_deref(), workflow: None, limit: 1 };
+                github::list_workflow_runs(&token, &owner, &repo_name, &filter)?
+                    .first()
+                    .map(|run| run.id)
+                    .ok_or_else(|| anyhow::anyhow!("No runs found for {}", branch.as_deref().unwrap_or("this repository")))
+            }
+        }
+    })?;
+    let token = auth::get_github_token()?;
+
+    info!("Watching run {} of {}/{}", run_id.to_string().cyan(), owner, repo_name);
+    let run = actions::watch(&token, &owner, &repo_name, run_id, Duration::from_secs(interval.max(1)))?;
+
+    // Failures are reported by the caller, through `RunView::ensure_succeeded`.
+    if actions::succeeded(run.conclusion.as_deref()) {
+        info!("{} Run {} succeeded", "✓".green(), run.id);
+    }
+    let jobs = github::list_run_jobs(&token, &owner, &repo_name, run_id)?;
+    Ok(RunView { run, jobs })
+}
+
+#[derive(Serialize)]
+pub struct RunActionResult {
+    pub run_id: u64,
+    pub action: &'static str,
+}
+
+pub fn run_rerun(repo: Option<&str>, run_id: u64, failed_only: bool) -> Result<RunActionResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    with_private_access(|| {
+        github::rerun_workflow_run(&auth::get_github_token()?, &owner, &repo_name, run_id, failed_only)
+    })?;
+
+    let what = if failed_only { "Failed jobs of run" } else { "Run" };
+    info!("{} {} {} queued again", "✓".green(), what, run_id.to_string().cyan());
+    Ok(RunActionResult { run_id, action: if failed_only { "rerun_failed" } else { "rerun" } })
+}
+
+pub fn run_cancel(repo: Option<&str>, run_id: u64) -> Result<RunActionResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    with_private_access(|| github::cancel_workflow_run(&auth::get_github_token()?, &owner, &repo_name, run_id))?;
+
+    info!("{} Cancellation of run {} requested", "✓".green(), run_id.to_string().cyan());
+    Ok(RunActionResult { run_id, action: "cancel" })
+}
+
+/// Writes the logs of every job of the run, or of the job named or numbered
+/// `job`, to stdout.
+pub fn run_logs(repo: Option<&str>, run_id: u64, job: Option<&str>) -> Result<()> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let mut jobs = with_private_access(|| github::list_run_jobs(&auth::get_github_token()?, &owner, &repo_name, run_id))?;
+
+    if let Some(job) = job {
+        jobs.retain(|candidate| candidate.name == job || candidate.id.to_string() == job);
+        if jobs.is_empty() {
+            return Err(anyhow::anyhow!("Run {} has no job {}", run_id, job));
+        }
+    }
+    actions::stream_logs(&auth::get_github_token()?, &owner, &repo_name, &jobs)
+}
+
+#[derive(Serialize)]
+pub struct WorkflowDispatchResult {
+    pub workflow: String,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub inputs: std::collections::BTreeMap<String, String>,
+}
+
+/// Triggers a `workflow_dispatch` run of `workflow` on `git_ref`, the current
+/// branch by default. Inputs are given as `key=value`.
+pub fn workflow_run(repo: Option<&str>, workflow: &str, git_ref: Option<&str>, fields: &[String]) -> Result<WorkflowDispatchResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref.to_string(),
+        None => git::current_branch().ok_or_else(|| anyhow::anyhow!("Not on a branch, pass --ref"))?,
+    };
+    let inputs = fields
+        .iter()
+        .map(|field| {
+            field
+                .split_once('=')
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Invalid input {}, expected key=value", field))
+        })
+        .collect::<Result<std::collections::BTreeMap<_, _>>>()?;
+
+    // Only the workflow file name is needed, whatever path it was given as.
+    let workflow = workflow.rsplit('/').next().unwrap_or(workflow);
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let request = github::DispatchRequest { git_ref: git_ref.clone(), inputs: inputs.clone() };
+    with_private_access(|| {
+        github::dispatch_workflow(&auth::get_github_token()?, &owner, &repo_name, workflow, &request)
+    })?;
+
+    info!("{} Dispatched {} on {}", "✓".green(), workflow.cyan().bold(), git_ref);
+    info!("Its run shows up in {} shortly", format!("kit run list --workflow {}", workflow).bold());
+    Ok(WorkflowDispatchResult { workflow: workflow.to_string(), git_ref, inputs })
+}
+
+pub struct ReleaseCreateOptions {
+    pub target: Option<String>,
+    pub title: Option<String>,
+    pub notes: Option<String>,
+    pub notes_file: Option<PathBuf>,
+    pub generate_notes: bool,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub assets: Vec<PathBuf>,
+    pub yes: bool,
+}
+
+/// Creates a release for `tag`, which GitHub creates from `target` (the
+/// default branch) when it doesn't exist yet. A release with assets stays a
+/// draft until they are all uploaded, so it is never seen half-published.
+pub fn release_create(repo: Option<&str>, tag: &str, options: &ReleaseCreateOptions) -> Result<github::Release> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    if let Some(path) = options.assets.iter().find(|path| !path.is_file()) {
+        return Err(anyhow::anyhow!("{} is not a file", path.display()));
+    }
+
+    let body = match (&options.notes, &options.notes_file) {
+        (Some(notes), _) => Some(notes.clone()),
+        (None, Some(path)) => Some(
+            fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?,
+        ),
+        (None, None) if options.generate_notes => {
+            let notes = release::generate_notes(tag)?;
+            info!("{}\n", notes);
+            confirm(&format!("Create {} with these notes?", tag), options.yes)?;
+            Some(notes)
+        }
+        (None, None) => None,
+    };
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let publish_after_upload = !options.draft && !options.assets.is_empty();
+    let request = github::CreateReleaseRequest {
+        tag_name: tag.to_string(),
+        target_commitish: options.target.clone(),
+        name: Some(options.title.clone().unwrap_or_else(|| tag.to_string())),
+        body,
+        draft: options.draft || publish_after_upload,
+        prerelease: options.prerelease,
+    };
+    let mut created = with_private_access(|| github::create_release(&auth::get_github_token()?, &owner, &repo_name, &request))?;
+    let token = auth::get_github_token()?;
+
+    match upload_assets(&token, &owner, &repo_name, &created, &options.assets) {
+        Ok(assets) => created.assets.extend(assets),
+        Err(e) => {
+            warn!(
+                "Release {} was left as a draft, upload the rest with {}",
+                tag,
+                format!("kit release upload {}", tag).bold()
+            );
+            return Err(e);
+        }
+    }
+    if publish_after_upload {
+        created = github::publish_release(&token, &owner, &repo_name, created.id)?;
+    }
+
+    let what = if created.draft { "draft release" } else { "release" };
+    info!("{} Created {} {}", "✓".green(), what, tag.cyan().bold());
+    info!("{}", created.html_url.bright_black());
+    Ok(created)
+}
+
+fn upload_assets(token: &str, owner: &str, repo_name: &str, target: &github::Release, paths: &[PathBuf]) -> Result<Vec<github::ReleaseAsset>> {
+    let mut uploaded = Vec::new();
+    for path in paths {
+        let asset = release::upload(token, owner, repo_name, target, path)?;
+        info!("{} Uploaded {} ({})", "✓".green(), asset.name, utils::format_size(asset.size));
+        uploaded.push(asset);
+    }
+    Ok(uploaded)
+}
+
+pub fn release_list(repo: Option<&str>, limit: u8) -> Result<Vec<github::Release>> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let releases = with_private_access(|| github::list_releases(&auth::get_github_token()?, &owner, &repo_name, limit))?;
+
+    if releases.is_empty() {
+        info!("{}/{} has no releases", owner, repo_name);
+    }
+    // Releases are listed newest first, so the first full release is the latest.
+    let latest = releases.iter().find(|release| !release.draft && !release.prerelease).map(|release| release.id);
+    for release in &releases {
+        let label = if release.draft {
+            "Draft".yellow()
+        } else if release.prerelease {
+            "Pre-release".yellow()
+        } else if Some(release.id) == latest {
+            "Latest".green()
+        } else {
+            "".normal()
+        };
+        info!(
+            "{:<16} {:<40} {:<11} {}",
+            release.tag_name.cyan(),
+            truncate(release.name.as_deref().unwrap_or(&release.tag_name), 40),
+            label,
+            release.published_at.as_deref().map(actions::short_time).unwrap_or_default().bright_black()
+        );
+    }
+    Ok(releases)
+}
+
+/// Shows the release for `tag`, or the latest release.
+pub fn release_view(repo: Option<&str>, tag: Option<&str>) -> Result<github::Release> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let shown = with_private_access(|| match tag {
+        Some(tag) => release::find(&auth::get_github_token()?, &owner, &repo_name, tag),
+        None => github::get_latest_release(&owner, &repo_name),
+    })?;
+
+    info!("{} {}", shown.name.as_deref().unwrap_or(&shown.tag_name).bold(), shown.tag_name.bright_black());
+    let state = if shown.draft {
+        "Draft".to_string()
+    } else {
+        let kind = if shown.prerelease { "Pre-release" } else { "Release" };
+        format!("{} published {}", kind, shown.published_at.as_deref().map(actions::short_time).unwrap_or_default())
+    };
+    info!("{}", state);
+    if let Some(body) = shown.body.as_deref().filter(|body| !body.trim().is_empty()) {
+        info!("");
+        info!("{}", body.trim());
+    }
+    if !shown.assets.is_empty() {
+        info!("");
+        info!("Assets:");
+        for asset in &shown.assets {
+            info!("  {:<40} {}", asset.name, utils::format_size(asset.size).bright_black());
+        }
+    }
+    info!("{}", shown.html_url.bright_black());
+
+    Ok(shown)
+}
+
+/// Adds files to the release for `tag`. Assets of the same name are only
+/// replaced with `clobber`.
+pub fn release_upload(repo: Option<&str>, tag: &str, files: &[PathBuf], clobber: bool) -> Result<Vec<github::ReleaseAsset>> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let target = with_private_access(|| release::find(&auth::get_github_token()?, &owner, &repo_name, tag))?;
+    let token = auth::get_github_token()?;
+
+    // Check every file before uploading any, so a clash doesn't leave the
+    // release with half the assets.
+    let mut uploads = Vec::new();
+    for path in files {
+        if !path.is_file() {
+            return Err(anyhow::anyhow!("{} is not a file", path.display()));
+        }
+        let name = utils::file_name(path)?;
+        let existing = target.assets.iter().find(|asset| asset.name == name);
+        if existing.is_some() && !clobber {
+            return Err(anyhow::anyhow!("{} already has an asset named {}, pass --clobber to replace it", tag, name));
+        }
+        uploads.push((path, existing));
+    }
+
+    let mut uploaded = Vec::new();
+    for (path, existing) in uploads {
+        let asset = match existing {
+            Some(existing) => release::replace(&token, &owner, &repo_name, &target, existing, path)?,
+            None => release::upload(&token, &owner, &repo_name, &target, path)?,
+        };
+        let verb = if existing.is_some() { "Replaced" } else { "Uploaded" };
+        info!("{} {} {} ({})", "✓".green(), verb, asset.name, utils::format_size(asset.size));
+        uploaded.push(asset);
+    }
+    Ok(uploaded)
+}
+
+#[derive(Serialize)]
+pub struct ReleaseDownloadResult {
+    pub tag: String,
+    pub files: Vec<PathBuf>,
+}
+
+/// Downloads the assets of the release for `tag` (the latest by default)
+/// matching any of `patterns`, or those built for this platform when none
+/// are given.
+pub fn release_download(repo: Option<&str>, tag: Option<&str>, patterns: &[String], dir: &Path) -> Result<ReleaseDownloadResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let source = with_private_access(|| match tag {
+        Some(tag) => release::find(&auth::get_github_token()?, &owner, &repo_name, tag),
+        None => github::get_latest_release(&owner, &repo_name),
+    })?;
+
+    let selected: Vec<&github::ReleaseAsset> = source
+        .assets
+        .iter()
+        .filter(|asset| match patterns {
+            [] => release::matches_platform(&asset.name),
+            patterns => patterns.iter().any(|pattern| utils::glob_match(pattern, &asset.name)),
+        })
+        .collect();
+    if selected.is_empty() {
+        let available: Vec<&str> = source.assets.iter().map(|asset| asset.name.as_str()).collect();
+        let wanted = if patterns.is_empty() { "this platform".to_string() } else { patterns.join(", ") };
+        return Err(anyhow::anyhow!(
+            "No assets of {} match {} (available: {})",
+            source.tag_name,
+            wanted,
+            if available.is_empty() { "none".to_string() } else { available.join(", ") }
+        ));
+    }
+
+    let token = auth::get_github_token()?;
+    let mut files = Vec::new();
+    for asset in selected {
+        let path = release::download(&token, &owner, &repo_name, asset, dir)?;
+        info!("{} Downloaded {} ({})", "✓".green(), path.display(), utils::format_size(asset.size));
+        files.push(path);
+    }
+    Ok(ReleaseDownloadResult { tag: source.tag_name, files })
+}
+
+#[derive(Serialize)]
+pub struct ReleaseDeleteResult {
+    pub tag: String,
+    pub deleted: bool,
+    pub tag_deleted: bool,
+}
+
+/// Deletes the release for `tag`, and with `cleanup_tag` the tag itself.
+pub fn release_delete(repo: Option<&str>, tag: &str, yes: bool, cleanup_tag: bool) -> Result<ReleaseDeleteResult> {
+    let (owner, repo_name) = repo_or_origin(repo)?;
+    let what = if cleanup_tag { "and its tag" } else { "(the tag is kept)" };
+    confirm(&format!("Delete release {} of {}/{} {}?", tag.bold(), owner, repo_name, what), yes)?;
+
+    auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+    let target = with_private_access(|| release::find(&auth::get_github_token()?, &owner, &repo_name, tag))?;
+    let token = auth::get_github_token()?;
+    github::delete_release(&token, &owner, &repo_name, target.id)?;
+    if cleanup_tag {
+        github::delete_tag(&token, &owner, &repo_name, tag)?;
+    }
+
+    info!("{} Deleted release {}{}", "✓".green(), tag.cyan().bold(), if cleanup_tag { " and its tag" } else { "" });
+    Ok(ReleaseDeleteResult { tag: tag.to_string(), deleted: true, tag_deleted: cleanup_tag })
+}
+
+#[derive(Serialize)]
+pub struct AskResult {
+    #[serde(flatten)]
+    pub suggestion: ask::Suggestion,
+    pub ran: bool,
+    pub exit_code: Option<i32>,
+    #[serde(skip)]
+    succeeded: bool,
+}
+
+impl AskResult {
+    /// Fails when the command ran and failed, or was killed by a signal, so
+    /// kit exits non-zero too.
+    pub fn ensure_succeeded(&self) -> Result<()> {
+        if !self.ran || self.succeeded {
+            return Ok(());
+        }
+        let reason = match self.exit_code {
+            Some(code) => format!("Command exited with {}", code),
+            None => "Command was killed by a signal".to_string(),
+        };
+        Err(output::Reported(reason).into())
+    }
+}
+
+/// Suggests a shell command for `request`, explains it and runs it once
+/// confirmed. Commands that can lose work are flagged and default to no.
+pub fn ai_ask(request: &str, model: Option<&str>) -> Result<AskResult> {
+    let suggestion = ask::suggest(request, model)?;
+
+    info!("{}", suggestion.command.cyan().bold());
+    info!("{}", suggestion.explanation);
+    if suggestion.destructive {
+        warn!("This command can discard work or rewrite history");
+    }
+
+    let mut result = AskResult { suggestion, ran: false, exit_code: None, succeeded: false };
+    if !io::stdin().is_terminal() {
+        info!("Not running it without a terminal to confirm on");
+        return Ok(result);
+    }
+    let prompt = if result.suggestion.destructive { "Run it anyway?" } else { "Run it?" };
+    // Never run on a bare Enter: the destructive check is only a heuristic.
+    if !Confirm::new(prompt).with_default(false).prompt()? {
+        return Ok(result);
+    }
+
+    let mut command = std::process::Command::new("sh");
+    command.arg("-c").arg(&result.suggestion.command);
+    // Keep stdout for the JSON result; the command's own output goes to stderr.
+    if output::format() == OutputFormat::Json {
+        command.stdout(io::stderr());
+    }
+    let status = command.status()?;
+    result.ran = true;
+    result.exit_code = status.code();
+    result.succeeded = status.success();
+    Ok(result)
+}
+
+#[derive(Serialize)]
+pub struct ExplainResult {
+    pub target: String,
+    #[serde(flatten)]
+    pub subject: explain::Subject,
+    pub explanation: String,
+}
+
+/// Explains a commit, a range of commits or a file (optionally some of its
+/// lines). The explanation is the result, so it goes to stdout: as plain
+/// text, or as Markdown with `--output markdown`.
+pub fn ai_explain(target: &str, model: Option<&str>) -> Result<ExplainResult> {
+    let subject = explain::resolve(target)?;
+    let explanation = explain::explain(&subject, model)?;
+
+    match output::format() {
+        OutputFormat::Text => println!("{}", explain::render_plain(&explanation)),
+        OutputFormat::Markdown => println!("{}", explanation),
+        OutputFormat::Json | OutputFormat::Quiet => {}
+    }
+    Ok(ExplainResult { target: target.to_string(), subject, explanation })
+}
+
+pub enum ReviewTarget {
+    Staged,
+    Range(String),
+    PullRequest(u64),
+}
+
+#[derive(Serialize)]
+pub struct ReviewResult {
+    pub target: String,
+    pub issues: Vec<review::Issue>,
+    pub review_url: Option<String>,
+}
+
+/// Has the AI provider review the staged changes, a range of commits or a
+/// pull request, and with `post` leaves the issues as review comments on
+/// the pull request.
+pub fn ai_review(repo: Option<&str>, target: &ReviewTarget, model: Option<&str>, post: bool, yes: bool) -> Result<ReviewResult> {
+    let (diff, described, pull) = match target {
+        ReviewTarget::Staged => (git::diff(None)?, "staged changes".to_string(), None),
+        ReviewTarget::Range(range) => (git::diff(Some(range))?, range.clone(), None),
+        ReviewTarget::PullRequest(number) => {
+            let (owner, repo_name) = repo_or_origin(repo)?;
+            if post {
+                auth::ensure_scopes(WRITE_REPO_SCOPES)?;
+            }
+            let token = auth::get_github_token()?;
+            let pull = github::get_pull_request(&token, &owner, &repo_name, *number)?;
+            let diff = github::get_pull_request_diff(&token, &owner, &repo_name, *number)?;
+            (diff, format!("#{} {}", number, pull.title), Some((owner, repo_name, token, pull)))
+        }
+    };
+
+    let files = review::split_diff(&diff);
+    if files.is_empty() {
+        return Err(match target {
+            ReviewTarget::Staged => anyhow::anyhow!("Nothing staged to review, stage changes or pass a range or --pr"),
+            _ => anyhow::anyhow!("No changes to review in {}", described),
+        });
+    }
+
+    let file_count = match files.len() {
+        1 => "1 file".to_string(),
+        count => format!("{} files", count),
+    };
+    info!("Reviewing {} ({})", described.bold(), file_count);
+    let settings = config::load_ai_settings()?;
+    let issues = review::review(&files, settings.context_tokens, model)?;
+
+    if issues.is_empty() {
+        info!("{} No issues found", "✓".green());
+    }
+    for issue in &issues {
+        let anchor = match issue.line {
+            Some(line) => format!("{}:{}", issue.file, line),
+            None => issue.file.clone(),
+        };
+        info!("{} {} {}", issue.severity.label(), anchor.cyan(), issue.comment);
+    }
+    if !issues.is_empty() {
+        info!("");
+        info!("Found {}", review::summary(&issues));
+    }
+
+    let mut review_url = None;
+    if let Some((owner, repo_name, token, pull)) = pull.filter(|_| post && !issues.is_empty()) {
+        confirm(&format!("Post a review with {} issues to #{}?", issues.len(), pull.number), yes)?;
+        let request = review::review_request(&pull, &files, &issues);
+        let posted = github::create_review(&token, &owner, &repo_name, pull.number, &request)?;
+        info!("{} Posted review {}", "✓".green(), posted.html_url.bright_black());
+        review_url = Some(posted.html_url);
+    }
+
+    Ok(ReviewResult { target: described, issues, review_url })
+}
+
+pub struct GistCreateOptions {
+    pub files: Vec<PathBuf>,
+    pub clipboard: bool,
+    pub filename: Option<String>,
+    pub description: Option<String>,
+    pub public: bool,
+}
+
+/// Creates a gist from files, or from the clipboard, and copies its URL to
+/// the clipboard.
+pub fn gist_create(options: &GistCreateOptions) -> Result<github::Gist> {
+    let mut files = std::collections::BTreeMap::new();
+    if options.clipboard {
+        let name = options.filename.clone().unwrap_or_else(|| CLIPBOARD_GIST_FILENAME.to_string());
+        files.insert(name, utils::read_clipboard()?);
+    }
+    for path in &options.files {
+        let name = utils::file_name(path)?;
+        let content = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        if files.insert(name.clone(), content).is_some() {
+            return Err(anyhow::anyhow!("More than one file is named {}", name));
+        }
+    }
+    // GitHub rejects gists with empty files, with a less helpful message.
+    if let Some((name, _)) = files.iter().find(|(_, content)| content.trim().is_empty()) {
+        return Err(anyhow::anyhow!("{} is empty", name));
+    }
+
+    auth::ensure_scopes(GIST_SCOPES)?;
+    let token = auth::get_github_token()?;
+    let request = github::CreateGistRequest {
+        description: options.description.clone(),
+        public: options.public,
+        files: files.into_iter().map(|(name, content)| (name, github::GistContent { content })).collect(),
+    };
+    let created = github::create_gist(&token, &request)?;
+
+    let visibility = if created.public { "public" } else { "secret" };
+    info!("{} Created {} gist {}", "✓".green(), visibility, created.html_url.cyan().bold());
+    match utils::copy_to_clipboard(&created.html_url) {
+        Ok(_) => info!("URL copied to clipboard"),
+        Err(e) => warn!("Failed to copy to clipboard: {}", e),
+    }
+    Ok(created)
+}
+
+pub fn gist_list(limit: u8) -> Result<Vec<github::Gist>> {
+    let token = auth::get_github_token()?;
+    let gists = github::list_gists(&token, limit)?;
+
+    if gists.is_empty() {
+        info!("You have no gists");
+    }
+    for listed in &gists {
+        let title = listed
+            .description
+            .as_deref()
+            .filter(|description| !description.is_empty())
+            .or_else(|| listed.files.keys().next().map(String::as_str))
+            .unwrap_or("-");
+        let files = match listed.files.len() {
+            1 => "1 file".to_string(),
+            count => format!("{} files", count),
+        };
+        info!(
+            "{:<32} {:<40} {:<8} {:<6} {}",
+            listed.id.cyan(),
+            truncate(title, 40),
+            files,
+            if listed.public { "public".normal() } else { "secret".yellow() },
+            actions::short_time(&listed.updated_at).bright_black()
+        );
+    }
+    Ok(gists)
+}
+
+/// Prints the files of a gist, or just `filename`, to stdout.
+pub fn gist_view(gist: &str, filename: Option<&str>) -> Result<github::Gist> {
+    let token = auth::get_github_token()?;
+    let mut shown = github::get_gist(&token, gist::gist_id(gist))?;
+    if let Some(filename) = filename {
+        if !shown.files.contains_key(filename) {
+            return Err(anyhow::anyhow!("Gist {} has no file {}", shown.id, filename));
+        }
+        shown.files.retain(|name, _| name == filename);
+    }
+
+    if let Some(description) = shown.description.as_deref().filter(|description| !description.is_empty()) {
+        info!("{}", description.bold());
+    }
+    for file in shown.files.values() {
+        if shown.files.len() > 1 {
+            info!("{}", file.filename.cyan().bold());
+        }
+        if file.truncated {
+            warn!("{} is too large to show in full, see {}", file.filename, shown.html_url);
+        }
+        // The contents are the result itself, so they go to stdout except
+        // when the result is printed as JSON instead.
+        if output::format() != OutputFormat::Json {
+            let content = file.content.as_deref().unwrap_or_default();
+            print!("{}", content);
+            if !content.ends_with('\n') {
+                println!();
+            }
+        }
+    }
+    Ok(shown)
+}
+
+pub struct GistEditOptions {
+    pub files: Vec<PathBuf>,
+    pub filename: Option<String>,
+    pub remove: Vec<String>,
+    pub description: Option<String>,
+}
+
+/// Replaces or adds files, removes files and changes the description of a
+/// gist. Given none of those, opens a file of the gist in the editor.
+pub fn gist_edit(gist: &str, options: &GistEditOptions) -> Result<github::Gist> {
+    auth::ensure_scopes(GIST_SCOPES)?;
+    let token = auth::get_github_token()?;
+    let current = github::get_gist(&token, gist::gist_id(gist))?;
+    let mut request = github::EditGistRequest { description: options.description.clone(), ..Default::default() };
+
+    for path in &options.files {
+        let content = fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        request.files.insert(utils::file_name(path)?, Some(github::GistContent { content }));
+    }
+    for name in &options.remove {
+        if !current.files.contains_key(name) {
+            return Err(anyhow::anyhow!("Gist {} has no file {}", current.id, name));
+        }
+        request.files.insert(name.clone(), None);
+    }
+
+    if request.files.is_empty() && request.description.is_none() {
+        let file = match &options.filename {
+            Some(filename) => current
+                .files
+                .get(filename)
+                .ok_or_else(|| anyhow::anyhow!("Gist {} has no file {}", current.id, filename))?,
+            None if current.files.len() == 1 => current.files.values().next().unwrap(),
+            None => return Err(anyhow::anyhow!("Gist {} has several files, pick one with --filename", current.id)),
+        };
+        if file.truncated {
+            return Err(anyhow::anyhow!("{} is too large to edit here", file.filename));
+        }
+
+        let original = file.content.as_deref().unwrap_or_default();
+        let edited = gist::edit_in_editor(&current.id, &file.filename, original)?;
+        if edited == original {
+            info!("No changes to {}", file.filename);
+            return Ok(current);
+        }
+        request.files.insert(file.filename.clone(), Some(github::GistContent { content: edited }));
+    }
+
+    let updated = github::update_gist(&token, &current.id, &request)?;
+    info!("{} Updated gist {}", "✓".green(), updated.html_url.cyan().bold());
+    Ok(updated)
+}
+
+#[derive(Serialize)]
+pub struct GistDeleteResult {
+    pub id: String,
+    pub deleted: bool,
+}
+
+pub fn gist_delete(gist: &str, yes: bool) -> Result<GistDeleteResult> {
+    let gist_id = gist::gist_id(gist);
+    confirm(&format!("Delete gist {}?", gist_id.bold()), yes)?;
+
+    auth::ensure_scopes(GIST_SCOPES)?;
+    let token = auth::get_github_token()?;
+    github::delete_gist(&token, gist_id)?;
+
+    info!("{} Deleted gist {}", "✓".green(), gist_id.cyan().bold());
+    Ok(GistDeleteResult { id: gist_id.to_string(), deleted: true })
+}
+
+#[derive(Serialize)]
+pub struct BrowseResult {
+    pub url: String,
+}
+
+/// Opens the current repository on GitHub: the branch being worked on, or a
+/// file (`path`) or line (`path:line`) in it.
+pub fn browse(target: Option<&str>, open_browser: bool) -> Result<BrowseResult> {
+    let (owner, repo_name) = repo_or_origin(None)?;
+    let url = format!("{}/{}/{}", config::endpoints().github_web, owner, repo_name);
+
+    // Detached HEADs have no branch on GitHub, but their commit may be there.
+    let revision = git::current_branch().or_else(git::head_commit);
+    let url = match (target, revision) {
+        (Some(target), Some(revision)) => {
+            let (path, line) = split_line(target);
+            let kind = if std::path::Path::new(path).is_dir() { "tree" } else { "blob" };
+            let prefix = git::path_prefix().unwrap_or_default();
+            let path = normalize_path(&format!("{}{}", prefix, path));
+            match line {
+                Some(line) => format!("{}/{}/{}/{}#L{}", url, kind, revision, path, line),
+                None => format!("{}/{}/{}/{}", url, kind, revision, path),
+            }
+        }
+        (Some(_), None) => return Err(anyhow::anyhow!("Nothing committed yet, there is no file to show on GitHub")),
+        (None, Some(revision)) => format!("{}/tree/{}", url, revision),
+        (None, None) => url,
+    };
+
+    if open_browser {
+        info!("Opening {}", url.underline());
+        if let Err(e) = open::that(&url) {
+            warn!("Could not open browser: {}", e);
+        }
+    } else {
+        info!("{}", url);
+    }
+    Ok(BrowseResult { url })
+}
+
+fn repo_or_origin(repo: Option<&str>) -> Result<(String, String)> {
+    match repo {
+        Some(repo) => resolve(repo).ok_or_else(|| anyhow::anyhow!("Invalid repository URL format")),
+        None => git::remote_url("origin")
+            .as_deref()
+            .and_then(resolve)
+            .ok_or_else(|| anyhow::anyhow!("No GitHub origin remote here, pass a repository explicitly")),
+    }
+}
+
+/// Splits a trailing `:<line>` off `target`.
+fn split_line(target: &str) -> (&str, Option<u32>) {
+    match target.rsplit_once(':') {
+        Some((path, line)) => match line.parse() {
+            Ok(line) => (path, Some(line)),
+            Err(_) => (target, None),
+        },
+        None => (target, None),
+    }
+}
+
+/// Resolves `.` and `..` components so paths given relative to a
+/// subdirectory map onto the repository.
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+#[derive(Serialize)]
+pub struct UpdateResult {
+    pub current_version: String,
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub updated: bool,
+}
+
+/// Updates kit to the latest GitHub release. With `check_only` it just
+/// reports whether one is available, and a failed lookup is only logged so
+/// the check never gets in the way of scripts calling it.
+pub fn self_update(check_only: bool) -> Result<UpdateResult> {
+    let mut result = UpdateResult {
+        current_version: CURRENT_VERSION.to_string(),
+        latest_version: None,
+        update_available: false,
+        updated: false,
+    };
+
+    let release = match github::get_latest_release(update::RELEASE_OWNER, update::RELEASE_REPO) {
+        Ok(release) => release,
+        Err(e) if check_only => {
+            warn!("Could not check for updates: {}", e);
+            return Ok(result);
         }
+        Err(e) => return Err(e),
+    };
+    result.latest_version = Some(release.tag_name.clone());
+    result.update_available = update::is_newer(&release.tag_name, CURRENT_VERSION);
+
+    if !result.update_available {
+        info!("{} kit {} is up to date", "✓".green(), CURRENT_VERSION);
+        return Ok(result);
+    }
+    if check_only {
+        info!(
+            "kit {} is available (you have {}). Run {} to update.",
+            release.tag_name.cyan().bold(),
+            CURRENT_VERSION,
+            "kit self-update".bold()
+        );
+        info!("Release notes: {}", release.html_url.bright_black());
+        return Ok(result);
+    }
+
+    let asset_name = update::asset_name();
+    let asset = release.assets.iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| anyhow::anyhow!("Release {} has no {} binary", release.tag_name, asset_name))?;
+    let checksums = release.assets.iter()
+        .find(|asset| asset.name == update::CHECKSUMS_ASSET)
+        .ok_or_else(|| anyhow::anyhow!(
+            "Release {} publishes no {}, refusing to install an unverified binary",
+            release.tag_name,
+            update::CHECKSUMS_ASSET
+        ))?;
+
+    let mut spinner = Spinner::new_with_stream(
+        spinners::Dots,
+        format!("Downloading {} ({:.1} MB)...", asset.name, asset.size as f64 / 1_000_000.0),
+        Color::Blue,
+        Streams::Stderr,
+    );
+    let downloaded = update::download(&checksums.browser_download_url).and_then(|listing| {
+        let listing = String::from_utf8_lossy(&listing).into_owned();
+        let binary = update::download(&asset.browser_download_url)?;
+        Ok((listing, binary))
+    });
+    let (listing, binary) = match downloaded {
+        Ok(downloaded) => downloaded,
+        Err(e) => {
+            spinner.fail("Download failed");
+            return Err(e);
+        }
+    };
+    spinner.stop();
+
+    let expected = update::expected_checksum(&listing, &asset.name)
+        .ok_or_else(|| anyhow::anyhow!("{} has no entry for {}", update::CHECKSUMS_ASSET, asset.name))?;
+    update::verify_checksum(&binary, &expected)?;
+    debug!("Checksum verified: {}", expected);
+
+    let path = update::replace_executable(&binary)?;
+    info!("{} Updated kit {} → {} at {}", "✓".green(), CURRENT_VERSION, release.tag_name.cyan().bold(), path.display());
+    result.updated = true;
+    Ok(result)
+}
+
+pub fn cache_stats() -> Result<cache::CacheStats> {
+    let stats = cache::stats()?;
+    info!(
+        "{} cached responses ({}), {} expired",
+        stats.entries.to_string().cyan().bold(),
+        utils::format_size(stats.bytes),
+        stats.expired
+    );
+    info!("Location: {}", stats.path.bright_black());
+    Ok(stats)
+}
+
+pub fn cache_clear() -> Result<cache::ClearResult> {
+    let result = cache::clear()?;
+    info!("{} Removed {} cached files ({})", "✓".green(), result.files, utils::format_size(result.bytes));
+    Ok(result)
+}
+
+#[derive(Serialize)]
+pub struct IpReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4: Option<ip::IpInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6: Option<ip::IpInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local: Option<Vec<ip::LocalAddress>>,
+}
+
+pub struct IpOptions {
+    pub copy: bool,
+    pub ipv4_only: bool,
+    pub ipv6_only: bool,
+    pub local: bool,
+}
+
+pub fn ip(options: &IpOptions) -> Result<IpReport> {
+    let lookup = |family, requested: bool| -> Option<ip::IpInfo> {
+        if !requested {
+            return None;
+        }
+        match ip::fetch_public_ip(family) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                // IPv6 is often simply unavailable, so only warn when it was asked for.
+                if family == ip::AddressFamily::V4 || options.ipv6_only {
+                    error!("Failed to fetch public {:?} address: {}", family, e);
+                } else {
+                    debug!("No public IPv6 address: {}", e);
+                }
+                None
+            }
+        }
+    };
 
-        // Copy to clipboard only if flag is set
-        if copy_to_clipboard {
-            match utils::copy_to_clipboard(&ip) {
+    let report = IpReport {
+        ipv4: lookup(ip::AddressFamily::V4, !options.ipv6_only),
+        ipv6: lookup(ip::AddressFamily::V6, !options.ipv4_only),
+        local: options.local.then(ip::local_addresses),
+    };
+
+    if report.ipv4.is_none() && report.ipv6.is_none() {
+        return Err(anyhow::anyhow!("Failed to fetch IP address"));
+    }
+
+    print_ip_report(&report);
+
+    // Copy to clipboard only if flag is set
+    if options.copy {
+        let primary = report.ipv4.as_ref().or(report.ipv6.as_ref());
+        if let Some(info) = primary {
+            match utils::copy_to_clipboard(&info.ip) {
                 std::result::Result::Ok(_) => info!("IP address copied to clipboard!"),
                 Err(e) => warn!("Failed to copy to clipboard: {}", e),
             }
         }
+    }
 
-        return Ok(());
+    Ok(report)
+}
+
+fn print_ip_report(report: &IpReport) {
+    for (label, info) in [("IPv4", &report.ipv4), ("IPv6", &report.ipv6)] {
+        let Some(info) = info else {
+            continue;
+        };
+        info!("Your {} address: {} {}", label, info.ip.cyan().bold(), format!("(via {})", info.provider).bright_black());
+
+        if let Some(loc) = &info.location {
+            info!("Location: {}", loc.yellow());
+        }
+
+        let details: Vec<String> = [
+            ("Colo", &info.colo),
+            ("HTTP", &info.http),
+            ("TLS", &info.tls),
+            ("WARP", &info.warp),
+        ]
+        .iter()
+        .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}: {}", name, v)))
+        .collect();
+        if !details.is_empty() {
+            info!("{}", details.join("  ").bright_black());
+        }
+    }
+
+    if let Some(local) = &report.local {
+        info!("Local addresses:");
+        for address in local {
+            info!("  {:<12} {}", address.interface, address.ip);
+        }
     }
+}
+
+pub fn net_dns(host: &str) -> Result<net::DnsResult> {
+    let result = net::resolve(host)?;
+    info!("{} resolved in {} ms", result.host.cyan().bold(), result.elapsed_ms);
+    for address in &result.addresses {
+        info!("  {}", address);
+    }
+    Ok(result)
+}
+
+pub fn net_port(host: &str, port: u16, timeout_secs: u64) -> Result<net::PortResult> {
+    let result = net::check_port(host, port, Duration::from_secs(timeout_secs))?;
+    info!(
+        "{} {}:{} is reachable via {} ({} ms)",
+        "✓".green(),
+        result.host,
+        result.port,
+        result.address.bright_black(),
+        result.elapsed_ms
+    );
+    Ok(result)
+}
+
+pub fn net_http(url: &str) -> Result<net::HttpResult> {
+    let result = net::inspect_http(url, NET_TIMEOUT)?;
+    info!("{} {} ({}) in {} ms", result.status.to_string().cyan().bold(), result.url, result.http_version, result.elapsed_ms);
 
-    error!("Could not find IP address in response");
-    Err(anyhow::anyhow!("Could not parse IP address from response"))
+    if let Some(certificate) = &result.certificate {
+        info!("Subject: {}", certificate.subject);
+        info!("Issuer: {}", certificate.issuer);
+        info!("Valid: {} → {}", certificate.not_before, certificate.not_after);
+        let days = format!("{} days", certificate.days_remaining);
+        match certificate.days_remaining {
+            d if d < 0 => error!("Certificate expired {} days ago", -d),
+            d if d < 14 => warn!("Certificate expires in {}", days.yellow()),
+            _ => info!("Expires in {}", days.green()),
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Serialize)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    pub fn ensure_passed(&self) -> Result<()> {
+        let failures = self.checks.iter().filter(|c| !c.ok).count();
+        if failures > 0 {
+            return Err(output::Reported(format!("{} of {} checks failed", failures, self.checks.len())).into());
+        }
+        Ok(())
+    }
+}
+
+/// Runs the connectivity checks kit's own commands depend on and reports
+/// each one. Callers decide whether failures are fatal via `ensure_passed`.
+pub fn net_doctor() -> Result<DoctorReport> {
+    let endpoints = config::endpoints();
+    let github_api_host = host_of(&endpoints.github_api)?;
+
+    let checks: Vec<(&str, Result<String>)> = vec![
+        ("DNS", net::resolve(&github_api_host).map(|r| {
+            format!("{} → {} ({} ms)", r.host, r.addresses.join(", "), r.elapsed_ms)
+        })),
+        ("GitHub API", net::inspect_http(&endpoints.github_api, NET_TIMEOUT).map(|r| {
+            format!("{} ({}, {} ms)", r.url, r.status, r.elapsed_ms)
+        })),
+        ("SSH", net::check_port(GITHUB_SSH_HOST, 22, NET_TIMEOUT).map(|r| {
+            format!("{}:{} ({} ms)", r.host, r.port, r.elapsed_ms)
+        })),
+        ("SSH over 443", net::check_port(GITHUB_SSH_OVER_HTTPS_HOST, 443, NET_TIMEOUT).map(|r| {
+            format!("{}:{} ({} ms)", r.host, r.port, r.elapsed_ms)
+        })),
+        // Any HTTP response, even a 401, means the provider is reachable.
+        ("AI provider", net::inspect_http(&format!("{}/models", endpoints.openai_api), NET_TIMEOUT).map(|r| {
+            format!("{} ({}, {} ms)", r.url, r.status, r.elapsed_ms)
+        })),
+    ];
+
+    let mut report = DoctorReport { checks: Vec::new() };
+    for (name, result) in checks {
+        let check = match result {
+            Ok(detail) => {
+                info!("{} {:<14} {}", "✓".green(), name, detail.bright_black());
+                DoctorCheck { name, ok: true, detail }
+            }
+            Err(e) => {
+                error!("{} {:<14} {}", "✗".red(), name, e);
+                DoctorCheck { name, ok: false, detail: e.to_string() }
+            }
+        };
+        report.checks.push(check);
+    }
+
+    if report.checks.iter().all(|c| c.ok) {
+        info!("All checks passed");
+    }
+    Ok(report)
+}
+
+fn host_of(url: &str) -> Result<String> {
+    reqwest::Url::parse(url)?
+        .host_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("{} has no host", url))
 }
 
 // https://github.com/kcterala/kcx.git
 // git@github.com:kcterala/kcx.git
-fn resolve(repo_url: &str) -> Option<(String, String)> {
+/// Splits a GitHub URL or `owner/repo` shorthand into owner and repository.
+pub fn resolve(repo_url: &str) -> Option<(String, String)> {
     if repo_url.starts_with("https://github.com/") {
         let path = repo_url.strip_prefix("https://github.com/")?;
         let path = path.strip_suffix(".git").unwrap_or(path);
@@ -140,7 +1816,56 @@ fn resolve(repo_url: &str) -> Option<(String, String)> {
           if parts.len() == 2 {
               return Some((parts[0].to_string(), parts[1].to_string()));
           }
+      } else if !repo_url.contains("://") && !repo_url.contains(':') {
+          let parts: Vec<&str> = repo_url.split("/").collect();
+          if parts.len() == 2 && parts.iter().all(|part| !part.is_empty()) {
+              return Some((parts[0].to_string(), parts[1].to_string()));
+          }
       }
 
       None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_accepts_https_and_ssh_urls() {
+        let expected = Some(("kcterala".to_string(), "kcx".to_string()));
+        assert_eq!(resolve("https://github.com/kcterala/kcx.git"), expected);
+        assert_eq!(resolve("git@github.com:kcterala/kcx.git"), expected);
+        assert_eq!(resolve("kcterala/kcx"), expected);
+        assert_eq!(resolve("https://gitlab.com/kcterala/kcx"), None);
+        assert_eq!(resolve("kcx"), None);
+    }
+
+    #[test]
+    fn topic_changes_keep_order_and_ignore_case() {
+        let current = vec!["git".to_string(), "example".to_string()];
+        let add = vec!["CLI".to_string(), "git".to_string()];
+        let remove = vec!["Example".to_string()];
+        assert_eq!(apply_topic_changes(&current, &add, &remove), vec!["git", "cli"]);
+    }
+
+    #[test]
+    fn browse_targets_split_lines_and_normalize_paths() {
+        assert_eq!(split_line("src/main.rs:42"), ("src/main.rs", Some(42)));
+        assert_eq!(split_line("src/main.rs"), ("src/main.rs", None));
+        assert_eq!(split_line("c:odd"), ("c:odd", None));
+        assert_eq!(normalize_path("src/commands/../main.rs"), "src/main.rs");
+        assert_eq!(normalize_path("src/./"), "src");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ask_fails_when_the_command_is_killed() {
+        let status = std::process::Command::new("sh").arg("-c").arg("kill -9 $$").status().unwrap();
+        let suggestion = ask::Suggestion { command: "true".to_string(), explanation: String::new(), destructive: false };
+        let result = AskResult { suggestion, ran: true, exit_code: status.code(), succeeded: status.success() };
+
+        let err = result.ensure_succeeded().unwrap_err();
+        assert!(err.is::<output::Reported>());
+        assert_eq!(err.to_string(), "Command was killed by a signal");
+    }
+}
@@ -0,0 +1,159 @@
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use reqwest::tls::TlsInfo;
+use serde::Serialize;
+use x509_parser::prelude::*;
+
+use crate::http;
+
+#[derive(Serialize, Debug)]
+pub struct DnsResult {
+    pub host: String,
+    pub addresses: Vec<String>,
+    pub elapsed_ms: u128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PortResult {
+    pub host: String,
+    pub port: u16,
+    pub address: String,
+    pub elapsed_ms: u128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HttpResult {
+    pub url: String,
+    pub status: u16,
+    pub http_version: String,
+    pub elapsed_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<CertificateInfo>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: String,
+    pub not_after: String,
+    /// Negative once the certificate has expired.
+    pub days_remaining: i64,
+}
+
+pub fn resolve(host: &str) -> Result<DnsResult> {
+    let started = Instant::now();
+    // The port is irrelevant, ToSocketAddrs just needs one to resolve.
+    let addresses: Vec<String> = (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| anyhow::anyhow!("Could not resolve {}: {}", host, e))?
+        .map(|addr| addr.ip().to_string())
+        .collect();
+
+    Ok(DnsResult {
+        host: host.to_string(),
+        addresses,
+        elapsed_ms: started.elapsed().as_millis(),
+    })
+}
+
+/// Connects to the first resolved address that accepts within `timeout`.
+pub fn check_port(host: &str, port: u16, timeout: Duration) -> Result<PortResult> {
+    let addresses: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| anyhow::anyhow!("Could not resolve {}: {}", host, e))?
+        .collect();
+
+    let mut last_error = None;
+    for address in addresses {
+        let started = Instant::now();
+        match TcpStream::connect_timeout(&address, timeout) {
+            Ok(_) => {
+                return Ok(PortResult {
+                    host: host.to_string(),
+                    port,
+                    address: address.to_string(),
+                    elapsed_ms: started.elapsed().as_millis(),
+                })
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match last_error {
+        Some(e) => Err(anyhow::anyhow!("Could not connect to {}:{}: {}", host, port, e)),
+        None => Err(anyhow::anyhow!("{} did not resolve to any address", host)),
+    }
+}
+
+/// Times a GET request to `url` and, for HTTPS, decodes the leaf certificate
+/// the server presented.
+pub fn inspect_http(url: &str, timeout: Duration) -> Result<HttpResult> {
+    let client = http::builder()
+        .timeout(timeout)
+        .tls_info(true)
+        .build()?;
+
+    let started = Instant::now();
+    let response = client.get(url)
+        .header("User-Agent", "kit-cli")
+        .send()?;
+    let elapsed_ms = started.elapsed().as_millis();
+
+    let certificate = response
+        .extensions()
+        .get::<TlsInfo>()
+        .and_then(|tls| tls.peer_certificate())
+        .map(parse_certificate)
+        .transpose()?;
+
+    Ok(HttpResult {
+        url: url.to_string(),
+        status: response.status().as_u16(),
+        http_version: format!("{:?}", response.version()),
+        elapsed_ms,
+        certificate,
+    })
+}
+
+fn parse_certificate(der: &[u8]) -> Result<CertificateInfo> {
+    let (_, certificate) = X509Certificate::from_der(der)
+        .map_err(|e| anyhow::anyhow!("Could not parse server certificate: {}", e))?;
+    let validity = certificate.validity();
+
+    let days_remaining = match validity.time_to_expiration() {
+        Some(remaining) => remaining.whole_days(),
+        None => (validity.not_after.to_datetime() - ASN1Time::now().to_datetime()).whole_days(),
+    };
+
+    Ok(CertificateInfo {
+        subject: certificate.subject().to_string(),
+        issuer: certificate.issuer().to_string(),
+        not_before: validity.not_before.to_string(),
+        not_after: validity.not_after.to_string(),
+        days_remaining,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn check_port_connects_to_listening_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = check_port("127.0.0.1", port, Duration::from_secs(1)).unwrap();
+        assert_eq!(result.address, format!("127.0.0.1:{}", port));
+    }
+
+    #[test]
+    fn resolve_handles_ip_literals() {
+        let result = resolve("127.0.0.1").unwrap();
+        assert_eq!(result.addresses, vec!["127.0.0.1"]);
+    }
+}
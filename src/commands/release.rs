@@ -0,0 +1,179 @@
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::debug;
+use reqwest::blocking::Body;
+
+use crate::commands::{ai, git};
+use crate::commands::github::{self, Release, ReleaseAsset};
+use crate::utils::{self, progress::{Progress, ProgressReader}};
+
+const NOTES_SYSTEM_PROMPT: &str = "You write release notes for a software project from its commit subjects. Group the changes under Markdown headings such as Features, Fixes and Other changes, one bullet per change, leaving out changes that don't matter to users. Output only the Markdown. No explanations.";
+const MAX_NOTES_TOKENS: u32 = 800;
+const MAX_NOTES_COMMITS: usize = 200;
+const MAX_DRAFT_LOOKUP: u8 = 100;
+const REPLACEMENT_SUFFIX: &str = ".kit-upload";
+
+// Names platforms go by in release asset names, the first one being Rust's.
+const OS_ALIASES: &[&[&str]] = &[&["linux"], &["macos", "darwin", "apple", "osx"], &["windows", "win", "win64"]];
+const ARCH_ALIASES: &[&[&str]] = &[&["x86_64", "amd64", "x64"], &["aarch64", "arm64"]];
+
+/// Drafts release notes for `tag` from the commits since the previous tag,
+/// or from the whole history when there is none.
+pub fn generate_notes(tag: &str) -> Result<String> {
+    let previous = git::latest_tag(tag);
+    let range = previous.as_deref().map(|previous| format!("{}..HEAD", previous)).unwrap_or_else(|| "HEAD".to_string());
+    let mut subjects = git::commit_subjects(&range);
+    if subjects.is_empty() {
+        return Err(anyhow::anyhow!("No commits since {}, nothing to write notes about", previous.as_deref().unwrap_or("the start")));
+    }
+    debug!("Writing notes from {} commits in {}", subjects.len(), range);
+
+    // The newest commits matter most when there are too many to send.
+    if subjects.len() > MAX_NOTES_COMMITS {
+        subjects.drain(..subjects.len() - MAX_NOTES_COMMITS);
+    }
+    let input = format!("Release {}\n\nCommits:\n{}", tag, subjects.join("\n"));
+    ai::generate(NOTES_SYSTEM_PROMPT, &input, MAX_NOTES_TOKENS, None, "Writing release notes...")
+}
+
+/// Looks a release up by tag, including drafts, which GitHub only returns
+/// when listing releases.
+pub fn find(token: &str, owner: &str, repo_name: &str, tag: &str) -> Result<Release> {
+    match github::get_release_by_tag(token, owner, repo_name, tag) {
+        Err(e) if github::is_not_found(&e) => github::list_releases(token, owner, repo_name, MAX_DRAFT_LOOKUP)?
+            .into_iter()
+            .find(|release| release.tag_name == tag)
+            .ok_or_else(|| anyhow::anyhow!("{}/{} has no release {}", owner, repo_name, tag)),
+        result => result,
+    }
+}
+
+/// Whether an asset name such as `kit-macos-arm64` is meant for the platform
+/// kit runs on. Names that mention no architecture match any.
+pub fn matches_platform(name: &str) -> bool {
+    matches_target(name, env::consts::OS, env::consts::ARCH)
+}
+
+fn matches_target(name: &str, os: &str, arch: &str) -> bool {
+    // `x86_64` would otherwise be split in two along with the separators.
+    let name = name.to_lowercase().replace("x86_64", "amd64");
+    let words: Vec<&str> = name.split(|c: char| !c.is_ascii_alphanumeric()).collect();
+    let mentions = |aliases: &[&str]| aliases.iter().any(|alias| words.contains(alias));
+
+    let os_matches = OS_ALIASES.iter().find(|aliases| aliases[0] == os).is_some_and(|aliases| mentions(aliases));
+    let arch_matches = match ARCH_ALIASES.iter().find(|aliases| aliases[0] == arch) {
+        Some(aliases) => mentions(aliases) || !ARCH_ALIASES.iter().any(|aliases| mentions(aliases)),
+        None => false,
+    };
+    os_matches && arch_matches
+}
+
+/// Uploads the file at `path` to `release` under its file name.
+pub fn upload(token: &str, owner: &str, repo_name: &str, release: &Release, path: &Path) -> Result<ReleaseAsset> {
+    upload_as(token, owner, repo_name, release, path, &utils::file_name(path)?)
+}
+
+fn upload_as(token: &str, owner: &str, repo_name: &str, release: &Release, path: &Path, name: &str) -> Result<ReleaseAsset> {
+    let file = File::open(path).map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let size = file.metadata()?.len();
+
+    let progress = Progress::new(&format!("Uploading {}", utils::file_name(path)?), size);
+    let body = Body::sized(ProgressReader::new(file, progress), size);
+    github::upload_release_asset(token, owner, repo_name, &release.upload_url, name, body)
+}
+
+/// Replaces `existing` with the file at `path`. The file goes up under a
+/// temporary name first, so the old asset is only deleted once its
+/// replacement is on GitHub; a failed upload leaves the release as it was.
+pub fn replace(token: &str, owner: &str, repo_name: &str, release: &Release, existing: &ReleaseAsset, path: &Path) -> Result<ReleaseAsset> {
+    let temporary = format!("{}{}", existing.name, REPLACEMENT_SUFFIX);
+    let uploaded = upload_as(token, owner, repo_name, release, path, &temporary)?;
+
+    let stranded = |e: anyhow::Error| anyhow::anyhow!("{}, the new {} is on the release as {}", e, existing.name, temporary);
+    github::delete_release_asset(token, owner, repo_name, existing.id).map_err(stranded)?;
+    github::rename_release_asset(token, owner, repo_name, uploaded.id, &existing.name).map_err(stranded)
+}
+
+/// Downloads `asset` into `dir`. The file only appears under its final name
+/// once complete, so an interrupted download never leaves a truncated asset.
+pub fn download(token: &str, owner: &str, repo_name: &str, asset: &ReleaseAsset, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(&asset.name);
+    let partial = dir.join(format!(".{}.part", asset.name));
+
+    let response = github::download_release_asset(token, owner, repo_name, asset.id)?;
+    let progress = Progress::new(&format!("Downloading {}", asset.name), asset.size);
+    let mut reader = ProgressReader::new(response, progress);
+    let copied = File::create(&partial).and_then(|mut file| io::copy(&mut reader, &mut file));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&partial);
+        return Err(anyhow::anyhow!("Download of {} failed: {}", asset.name, e));
+    }
+
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    #[test]
+    fn matches_target_understands_platform_aliases() {
+        assert!(matches_target("kit-macos-arm64", "macos", "aarch64"));
+        assert!(matches_target("kit_Darwin_x86_64.tar.gz", "macos", "x86_64"));
+        assert!(matches_target("kit-linux.tar.gz", "linux", "aarch64"));
+        assert!(!matches_target("kit-macos-amd64", "macos", "aarch64"));
+        assert!(!matches_target("kit-darwin-arm64", "windows", "aarch64"));
+        assert!(!matches_target("SHA256SUMS", "linux", "x86_64"));
+    }
+
+    #[test]
+    fn replace_deletes_the_old_asset_only_after_uploading() {
+        let server = test_support::server();
+        server.route(
+            "POST",
+            "/api/uploads/repos/octocat/kit/releases/184212311/assets",
+            &["http/bad_gateway", "github/release_asset_uploaded"],
+            None,
+        );
+        server.route("DELETE", "/api/repos/octocat/kit/releases/assets/301944101", &["github/no_content"], None);
+        server.route("PATCH", "/api/repos/octocat/kit/releases/assets/301944200", &["github/release_asset_renamed"], None);
+
+        let path = env::temp_dir().join(format!("kit-replace-{}", std::process::id())).join("kit-macos-amd64");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "new build").unwrap();
+        let release = Release {
+            id: 184212311,
+            tag_name: "v0.2.0".to_string(),
+            name: None,
+            body: None,
+            draft: false,
+            prerelease: false,
+            html_url: "https://github.com/octocat/kit/releases/tag/v0.2.0".to_string(),
+            upload_url: format!("{}/api/uploads/repos/octocat/kit/releases/184212311/assets{{?name,label}}", server.base_url),
+            published_at: None,
+            assets: Vec::new(),
+        };
+        let existing = ReleaseAsset {
+            id: 301944101,
+            name: "kit-macos-amd64".to_string(),
+            browser_download_url: String::new(),
+            size: 4812336,
+        };
+
+        assert!(replace("test-token", "octocat", "kit", &release, &existing, &path).is_err());
+        assert!(server.requests_to("/api/repos/octocat/kit/releases/assets/301944101").is_empty());
+
+        let replaced = replace("test-token", "octocat", "kit", &release, &existing, &path).unwrap();
+        assert_eq!((replaced.id, replaced.name.as_str()), (301944200, "kit-macos-amd64"));
+        assert_eq!(server.requests_to("/api/repos/octocat/kit/releases/assets/301944101").len(), 1);
+        let rename = &server.requests_to("/api/repos/octocat/kit/releases/assets/301944200")[0];
+        assert_eq!(rename.body, r#"{"name":"kit-macos-amd64"}"#);
+    }
+}
@@ -0,0 +1,348 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use anyhow::Result;
+use colored::*;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::ai;
+use crate::commands::github::{CreateReviewRequest, PullRequest, ReviewComment};
+
+const REVIEW_SYSTEM_PROMPT: &str = "You are a careful code reviewer. Review the diff for bugs, security problems, performance issues and confusing code. Lines of the new version are prefixed with their line number. Only report real problems in the changed lines, not matters of taste. Output only a JSON array of objects with the fields file, line (a line number from the diff, or null), severity (critical, major, minor or nit) and comment. Output [] when there is nothing to report. No explanations.";
+const MAX_REVIEW_TOKENS: u32 = 1500;
+const TRUNCATED_HUNK: &str = "[rest of hunk left out]\n";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    Major,
+    Minor,
+    Nit,
+}
+
+// Models don't always stick to the names they were given.
+impl From<String> for Severity {
+    fn from(severity: String) -> Severity {
+        match severity.to_lowercase().as_str() {
+            "critical" | "blocker" | "high" => Severity::Critical,
+            "major" | "medium" => Severity::Major,
+            "nit" | "trivial" | "info" => Severity::Nit,
+            _ => Severity::Minor,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Critical => "critical",
+            Severity::Major => "major",
+            Severity::Minor => "minor",
+            Severity::Nit => "nit",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Severity {
+    pub fn label(self) -> ColoredString {
+        let label = format!("{:<8}", self.to_string());
+        match self {
+            Severity::Critical => label.red().bold(),
+            Severity::Major => label.red(),
+            Severity::Minor => label.yellow(),
+            Severity::Nit => label.bright_black(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Issue {
+    pub file: String,
+    pub line: Option<u32>,
+    #[serde(deserialize_with = "severity")]
+    pub severity: Severity,
+    pub comment: String,
+}
+
+fn severity<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Severity, D::Error> {
+    Ok(Severity::from(String::deserialize(deserializer)?))
+}
+
+/// The changes to one file, with each hunk's lines numbered as in the new
+/// version of the file.
+pub struct FileDiff {
+    pub path: String,
+    pub hunks: Vec<String>,
+    /// New-side lines shown in the diff, the only ones GitHub accepts review
+    /// comments on.
+    pub lines: BTreeSet<u32>,
+}
+
+/// Splits a unified diff into files, leaving out binary files and files
+/// that were only deleted or renamed.
+pub fn split_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut new_line = 0;
+    let mut in_header = false;
+
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FileDiff { path: String::new(), hunks: Vec::new(), lines: BTreeSet::new() });
+            in_header = true;
+            continue;
+        }
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        // A deleted file's new side is `/dev/null`, so it keeps no path.
+        if in_header
+            && let Some(path) = line.strip_prefix("+++ ").filter(|path| *path != "/dev/null")
+        {
+            file.path = path.strip_prefix("b/").unwrap_or(path).to_string();
+        }
+        if line.starts_with("@@") {
+            in_header = false;
+            new_line = hunk_start(line);
+            file.hunks.push(format!("{}\n", line));
+            continue;
+        }
+        let Some(hunk) = file.hunks.last_mut().filter(|_| !in_header) else {
+            continue;
+        };
+
+        match line.chars().next() {
+            Some('+') | Some(' ') => {
+                hunk.push_str(&format!("{:>5} {}\n", new_line, line));
+                file.lines.insert(new_line);
+                new_line += 1;
+            }
+            _ => hunk.push_str(&format!("{:>5} {}\n", "", line)),
+        }
+    }
+
+    files.retain(|file| !file.path.is_empty() && !file.hunks.is_empty());
+    files
+}
+
+/// The first new-side line of a hunk, from `@@ -12,7 +14,9 @@`.
+fn hunk_start(header: &str) -> u32 {
+    header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .unwrap_or(1)
+}
+
+pub struct Chunk {
+    pub files: Vec<String>,
+    pub text: String,
+}
+
+/// Packs files into chunks of at most `budget` characters. A file too large
+/// for one chunk is split between hunks, and a hunk too large on its own is
+/// cut short.
+pub fn chunks(files: &[FileDiff], budget: usize) -> Vec<Chunk> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut current = Chunk { files: Vec::new(), text: String::new() };
+
+    for file in files {
+        let header = format!("File: {}\n", file.path);
+        let mut part = header.clone();
+        for hunk in &file.hunks {
+            let hunk = truncate_hunk(hunk, budget.saturating_sub(header.len()));
+            if part.len() > header.len() && part.len() + hunk.len() > budget {
+                push_part(&mut chunks, &mut current, &file.path, std::mem::replace(&mut part, header.clone()), budget);
+            }
+            part.push_str(&hunk);
+        }
+        push_part(&mut chunks, &mut current, &file.path, part, budget);
+    }
+
+    if !current.text.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn push_part(chunks: &mut Vec<Chunk>, current: &mut Chunk, path: &str, part: String, budget: usize) {
+    if !current.text.is_empty() && current.text.len() + part.len() + 1 > budget {
+        chunks.push(std::mem::replace(current, Chunk { files: Vec::new(), text: String::new() }));
+    }
+    if !current.text.is_empty() {
+        current.text.push('\n');
+    }
+    current.text.push_str(&part);
+    if current.files.last().is_none_or(|last| last != path) {
+        current.files.push(path.to_string());
+    }
+}
+
+fn truncate_hunk(hunk: &str, budget: usize) -> String {
+    if hunk.len() <= budget {
+        return hunk.to_string();
+    }
+    let mut truncated = String::new();
+    for line in hunk.lines() {
+        if truncated.len() + line.len() + 1 + TRUNCATED_HUNK.len() > budget {
+            break;
+        }
+        truncated.push_str(line);
+        truncated.push('\n');
+    }
+    truncated.push_str(TRUNCATED_HUNK);
+    truncated
+}
+
+/// Reviews each chunk of the diff in turn, keeping what the other chunks
+/// found when the answer for one can't be used.
+pub fn review(files: &[FileDiff], context_tokens: usize, model: Option<&str>) -> Result<Vec<Issue>> {
+    let budget = ai::context_budget(context_tokens, REVIEW_SYSTEM_PROMPT);
+    let chunks = chunks(files, budget);
+    let mut issues = Vec::new();
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        let message = match chunks.len() {
+            1 => format!("Reviewing {}...", describe(&chunk.files)),
+            count => format!("Reviewing {} ({}/{})...", describe(&chunk.files), index + 1, count),
+        };
+        let answer = ai::generate(REVIEW_SYSTEM_PROMPT, &chunk.text, MAX_REVIEW_TOKENS, model, &message)?;
+        match serde_json::from_str::<Vec<Issue>>(ai::strip_code_fence(&answer)) {
+            Ok(found) => issues.extend(found),
+            Err(e) => warn!("Could not read the review of {}: {}", describe(&chunk.files), e),
+        }
+    }
+
+    issues.sort_by(|a, b| (a.severity, &a.file, a.line).cmp(&(b.severity, &b.file, b.line)));
+    Ok(issues)
+}
+
+fn describe(files: &[String]) -> String {
+    match files {
+        [file] => file.clone(),
+        [first, rest @ ..] => format!("{} and {} more", first, rest.len()),
+        [] => "diff".to_string(),
+    }
+}
+
+/// Counts of issues per severity, most severe first, e.g. `1 critical, 2 nit`.
+pub fn summary(issues: &[Issue]) -> String {
+    let mut counts: BTreeMap<Severity, usize> = BTreeMap::new();
+    for issue in issues {
+        *counts.entry(issue.severity).or_default() += 1;
+    }
+    counts
+        .iter()
+        .map(|(severity, count)| format!("{} {}", count, severity))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A comment-only review of `pull` with the issues as line comments. Issues
+/// GitHub can't anchor, on lines outside the diff, go in the review's body.
+pub fn review_request(pull: &PullRequest, files: &[FileDiff], issues: &[Issue]) -> CreateReviewRequest {
+    let mut body = format!("Automated review: {}.", summary(issues));
+    let mut comments = Vec::new();
+
+    for issue in issues {
+        let anchored = issue.line.filter(|line| {
+            files.iter().any(|file| file.path == issue.file && file.lines.contains(line))
+        });
+        match anchored {
+            Some(line) => comments.push(ReviewComment {
+                path: issue.file.clone(),
+                line,
+                side: "RIGHT",
+                body: format!("**{}**: {}", issue.severity, issue.comment),
+            }),
+            None => {
+                let anchor = issue.line.map(|line| format!("{}:{}", issue.file, line)).unwrap_or_else(|| issue.file.clone());
+                body.push_str(&format!("\n- **{}** `{}`: {}", issue.severity, anchor, issue.comment));
+            }
+        }
+    }
+
+    CreateReviewRequest { commit_id: pull.head.sha.clone(), body, event: "COMMENT", comments }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 3f1c2ab..9d04e11 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,4 @@ fn parse() {
+     let a = 1;
+-    let b = 2;
++    let b = a.checked_add(2).unwrap();
++    let c = b;
+ }
+diff --git a/logo.png b/logo.png
+Binary files a/logo.png and b/logo.png differ
+";
+
+    #[test]
+    fn split_diff_numbers_new_lines_and_skips_binaries() {
+        let files = split_diff(DIFF);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert_eq!(files[0].lines, BTreeSet::from([10, 11, 12, 13]));
+        assert!(files[0].hunks[0].contains("   11 +    let b = a.checked_add(2).unwrap();"));
+    }
+
+    #[test]
+    fn split_diff_skips_deleted_files() {
+        let deleted = "\
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+--- a/src/old.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn old() {}
+-
+";
+        let files = split_diff(&format!("{}{}", deleted, DIFF));
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/lib.rs");
+    }
+
+    #[test]
+    fn chunks_stay_within_budget() {
+        let files = split_diff(&DIFF.repeat(3).replace("src/lib.rs", "src/a.rs"));
+        let chunks = chunks(&files, 250);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.text.len() <= 250));
+        assert!(chunks[0].text.starts_with("File: src/a.rs\n"));
+    }
+
+    #[test]
+    fn issues_accept_unexpected_severities() {
+        let answer = r#"```json
+[{"file": "src/lib.rs", "line": 11, "severity": "High", "comment": "Panics on overflow"}]
+```"#;
+        let issues: Vec<Issue> = serde_json::from_str(ai::strip_code_fence(answer)).unwrap();
+        assert_eq!(issues[0].severity, Severity::Critical);
+        assert_eq!(summary(&issues), "1 critical");
+    }
+
+    #[test]
+    fn review_request_keeps_lines_outside_the_diff_in_the_body() {
+        let files = split_diff(DIFF);
+        let pull: PullRequest = serde_json::from_value(serde_json::json!({
+            "number": 42, "title": "Parse safely", "head": { "sha": "9d04e11" }
+        }))
+        .unwrap();
+        let issue = |line, comment: &str| Issue { file: "src/lib.rs".to_string(), line, severity: Severity::Major, comment: comment.to_string() };
+
+        let request = review_request(&pull, &files, &[issue(Some(11), "Panics on overflow"), issue(Some(40), "Unused")]);
+        assert_eq!(request.comments.len(), 1);
+        assert_eq!(request.comments[0].line, 11);
+        assert_eq!(request.body, "Automated review: 2 major.\n- **major** `src/lib.rs:40`: Unused");
+    }
+}
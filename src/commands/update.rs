@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+use anyhow::Result;
+use log::debug;
+
+use crate::http::{self, SendWithRetry};
+use crate::utils;
+
+pub const RELEASE_OWNER: &str = "kcterala";
+pub const RELEASE_REPO: &str = "kit";
+pub const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// Name of the release binary for the platform kit was built for, following
+/// the `kit-<os>-<arch>` scheme the release workflow uses.
+pub fn asset_name() -> String {
+    let arch = match env::consts::ARCH {
+        "aarch64" => "arm64",
+        "x86_64" => "amd64",
+        other => other,
+    };
+    format!("kit-{}-{}{}", env::consts::OS, arch, env::consts::EXE_SUFFIX)
+}
+
+/// Compares release tags such as `v0.2.0` numerically, ignoring pre-release
+/// and build suffixes.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let core = version.split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    debug!("Downloading {}", url);
+    let response = http::get_streaming_client()
+        .get(url)
+        .header("User-Agent", "kit-cli")
+        .send_with_retry()?;
+
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("Download of {} failed: {}", url, response.status()));
+    }
+    Ok(response.bytes()?.to_vec())
+}
+
+/// Finds `asset` in a `sha256sum`-style listing (`<hex>  <name>`, binary
+/// mode names prefixed with `*`).
+pub fn expected_checksum(checksums: &str, asset: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        let name = name.trim_start();
+        (name.strip_prefix('*').unwrap_or(name) == asset).then(|| hash.to_lowercase())
+    })
+}
+
+pub fn verify_checksum(data: &[u8], expected: &str) -> Result<()> {
+    let actual = utils::sha256_hex(data);
+
+    if actual != expected {
+        return Err(anyhow::anyhow!("Checksum mismatch: expected {}, got {}", expected, actual));
+    }
+    Ok(())
+}
+
+/// Swaps the running executable for `binary`. The new file is written next
+/// to the old one and renamed over it, so an interrupted update never leaves
+/// a half-written kit behind.
+pub fn replace_executable(binary: &[u8]) -> Result<PathBuf> {
+    let current = env::current_exe()?.canonicalize()?;
+    let dir = current
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the directory of {}", current.display()))?;
+    let file_name = current
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the file name of {}", current.display()))?;
+    let staged = dir.join(format!(".{}.new", file_name.to_string_lossy()));
+
+    fs::write(&staged, binary)
+        .map_err(|e| anyhow::anyhow!("Could not write to {}: {}", dir.display(), e))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Windows refuses to overwrite a running executable but does allow
+    // renaming it out of the way.
+    #[cfg(windows)]
+    {
+        let old = current.with_extension("old.exe");
+        let _ = fs::remove_file(&old);
+        fs::rename(&current, &old)?;
+    }
+
+    if let Err(e) = fs::rename(&staged, &current) {
+        let _ = fs::remove_file(&staged);
+        return Err(anyhow::anyhow!("Could not replace {}: {}", current.display(), e));
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_newer_compares_numerically() {
+        assert!(is_newer("v0.10.0", "0.9.3"));
+        assert!(is_newer("v1.0.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.2.0-beta.1", "0.2.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn checksum_is_found_and_verified() {
+        let checksums = "\
+            2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  kit-macos-arm64\n\
+            486ea46224d1bb4fb680f34f7c9ad96a8f24ec88be73ea8e5a6c65260e9cb8a7 *kit-macos-amd64\n";
+
+        let expected = expected_checksum(checksums, "kit-macos-arm64").unwrap();
+        assert!(verify_checksum(b"hello", &expected).is_ok());
+        assert!(verify_checksum(b"hello!", &expected).is_err());
+        assert!(expected_checksum(checksums, "kit-macos-amd64").is_some());
+        assert_eq!(expected_checksum(checksums, "kit-linux-amd64"), None);
+    }
+}
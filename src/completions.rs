@@ -0,0 +1,150 @@
+//! Shell completion support.
+//!
+//! `kit completions <shell>` prints a small registration script; the shell
+//! then calls back into kit with `COMPLETE=<shell>` on every <TAB> and the
+//! completers below supply repository and branch names. They run while the
+//! user is typing, so they never prompt and fall back to cached or empty
+//! results instead of failing.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use anyhow::Result;
+use clap_complete::env::Shells;
+use clap_complete::{CompletionCandidate, Shell};
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::cache;
+use crate::commands::{self, git, github, graphql};
+
+pub const COMPLETE_ENV: &str = "COMPLETE";
+const REPO_CACHE_FILE: &str = "repos.json";
+const REPO_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const GITHUB_URL_PREFIX: &str = "https://github.com/";
+// Enough for anyone's recent work without paging through huge organizations.
+const MAX_COMPLETION_REPOS: usize = 500;
+
+#[derive(Serialize, Deserialize)]
+struct RepoCache {
+    fetched_at: u64,
+    repos: Vec<String>,
+}
+
+pub fn print_registration(shell: Shell) -> Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&shell.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Dynamic completion is not supported for {}", shell))?;
+    completer.write_registration(COMPLETE_ENV, "kit", "kit", "kit", &mut io::stdout())?;
+    Ok(())
+}
+
+/// Completes `owner/repo` from the repositories the user can access, and full
+/// URLs once `https://github.com/` has been typed.
+pub fn complete_repos(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let (prefix, partial) = match current.strip_prefix(GITHUB_URL_PREFIX) {
+        Some(rest) => (GITHUB_URL_PREFIX, rest),
+        None => ("", current),
+    };
+
+    cached_repos()
+        .into_iter()
+        .filter(|repo| repo.starts_with(partial))
+        .map(|repo| CompletionCandidate::new(format!("{}{}", prefix, repo)))
+        .collect()
+}
+
+/// Completes branches of the repository named earlier on the command line,
+/// or of the repository in the current directory when there isn't one.
+pub fn complete_branches(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+    let branches = match repo_on_command_line() {
+        Some((owner, repo_name)) => remote_branches(&owner, &repo_name),
+        None => git::local_branches(),
+    };
+
+    branches
+        .into_iter()
+        .filter(|branch| branch.starts_with(current))
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+// The shell passes the words typed so far back to kit as arguments.
+fn repo_on_command_line() -> Option<(String, String)> {
+    let args: Vec<String> = env::args().collect();
+    let subcommand = args.iter().position(|arg| arg == "clone" || arg == "fork")?;
+    args[subcommand + 1..]
+        .iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .find_map(|arg| commands::resolve(arg))
+}
+
+fn remote_branches(owner: &str, repo_name: &str) -> Vec<String> {
+    let Some(token) = auth::existing_github_token() else {
+        return Vec::new();
+    };
+    github::list_branches(&token, owner, repo_name)
+        .map(|branches| branches.into_iter().map(|branch| branch.name).collect())
+        .unwrap_or_default()
+}
+
+// Listing repositories takes a round trip to GitHub, too slow to repeat on
+// every keypress, so the list is cached for a day. A stale list still beats
+// nothing when GitHub can't be reached.
+fn cached_repos() -> Vec<String> {
+    let cached = read_repo_cache();
+    if let Some(cache) = &cached
+        && now().saturating_sub(cache.fetched_at) < REPO_CACHE_TTL.as_secs()
+    {
+        return cache.repos.clone();
+    }
+
+    match fetch_repos() {
+        Some(repos) => {
+            let _ = write_repo_cache(&repos);
+            repos
+        }
+        None => cached.map(|cache| cache.repos).unwrap_or_default(),
+    }
+}
+
+fn fetch_repos() -> Option<Vec<String>> {
+    let token = auth::existing_github_token()?;
+    graphql::list_viewer_repos(&token, MAX_COMPLETION_REPOS).ok()
+}
+
+fn repo_cache_path() -> Option<PathBuf> {
+    Some(cache::dir()?.join(REPO_CACHE_FILE))
+}
+
+fn read_repo_cache() -> Option<RepoCache> {
+    let contents = fs::read_to_string(repo_cache_path()?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_repo_cache(repos: &[String]) -> Result<()> {
+    let path = repo_cache_path().ok_or_else(|| anyhow::anyhow!("Could not find cache directory"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let cache = RepoCache { fetched_at: now(), repos: repos.to_vec() };
+    fs::write(path, serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
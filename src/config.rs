@@ -1,4 +1,6 @@
-use std::{fs, path::PathBuf};
+use std::collections::BTreeMap;
+use std::{env, fs, path::PathBuf};
+use std::sync::OnceLock;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -12,9 +14,159 @@ pub struct Config {
     pub github_username: String,
     #[serde(default)]
     pub openai_api_key: String,
+    #[serde(default)]
+    pub ai: AiSettings,
+    #[serde(default)]
+    pub endpoints: EndpointOverrides,
+    #[serde(default)]
+    pub github_app: Option<GithubAppConfig>,
+    /// Extra OAuth scopes requested on every device-flow login.
+    #[serde(default)]
+    pub oauth_scopes: Vec<String>,
+    /// Plain-text "what is my IP" service tried when Cloudflare is unreachable.
+    #[serde(default)]
+    pub ip_fallback_url: Option<String>,
+    /// Fall back to `git credential fill` when no other token is available.
+    #[serde(default)]
+    pub git_credential_helper: bool,
+    /// Extra commands, e.g. `"prs": "!gh pr list"` or `"myip": "ip -4 -c"`.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    #[serde(default)]
+    pub network: NetworkSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+}
+
+/// Authenticate as a GitHub App installation instead of a user.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GithubAppConfig {
+    pub app_id: String,
+    pub installation_id: u64,
+    pub private_key_path: PathBuf,
+}
+
+/// Base URL overrides from `config.json`. Each can also be set through the
+/// matching `KIT_*_URL` environment variable, which takes precedence.
+#[derive(Serialize, Deserialize, Default)]
+pub struct EndpointOverrides {
+    pub github_api: Option<String>,
+    pub github_web: Option<String>,
+    pub openai_api: Option<String>,
+    pub ip_trace: Option<String>,
+    pub ip_trace_v6: Option<String>,
+}
+
+pub struct Endpoints {
+    pub github_api: String,
+    pub github_web: String,
+    pub openai_api: String,
+    pub ip_trace: String,
+    pub ip_trace_v6: String,
+}
+
+const DEFAULT_GITHUB_API_URL: &str = "https://api.github.com";
+const DEFAULT_GITHUB_WEB_URL: &str = "https://github.com";
+const DEFAULT_OPENAI_API_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_IP_TRACE_URL: &str = "https://1.1.1.1/cdn-cgi/trace";
+const DEFAULT_IP_TRACE_V6_URL: &str = "https://[2606:4700:4700::1111]/cdn-cgi/trace";
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+static ENDPOINTS: OnceLock<Endpoints> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AiSettings {
+    pub model: String,
+    pub suggestions: usize,
+    pub temperature: f32,
+    pub max_regenerations: usize,
+    /// Whether the provider honors the `n` parameter. When it doesn't, all
+    /// suggestions are requested as a JSON list in a single completion.
+    pub multiple_choices: bool,
+    /// Tokens of input sent in one request. Longer inputs, such as large
+    /// diffs to review, are split into several requests.
+    pub context_tokens: usize,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        AiSettings {
+            model: "gpt-4.1-mini".to_string(),
+            suggestions: 3,
+            temperature: 0.8,
+            max_regenerations: 3,
+            multiple_choices: true,
+            context_tokens: 32_000,
+        }
+    }
+}
+
+/// Timeouts, retries and proxy settings for outgoing requests.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct NetworkSettings {
+    pub timeouts: ServiceTimeouts,
+    /// How often a failed request is retried before giving up.
+    pub max_retries: Option<u32>,
+    /// Proxy for every request. HTTP_PROXY, HTTPS_PROXY and NO_PROXY are
+    /// used when this isn't set.
+    pub proxy: Option<String>,
+    /// PEM file with extra root certificates, e.g. a corporate CA. Can also
+    /// be set through `KIT_CA_BUNDLE`, which takes precedence.
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// Request timeouts in seconds.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ServiceTimeouts {
+    pub github: u64,
+    /// An idle timeout: it bounds the wait for the response headers, then
+    /// each read of the streamed body, so a long completion that keeps
+    /// streaming is never cut off.
+    pub openai: u64,
+    pub ip: u64,
+}
+
+impl Default for ServiceTimeouts {
+    fn default() -> Self {
+        ServiceTimeouts {
+            github: 10,
+            openai: 60,
+            ip: 10,
+        }
+    }
+}
+
+/// On-disk cache for GitHub API responses.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub enabled: bool,
+    /// Seconds a cached response is used without asking GitHub. After that it
+    /// is revalidated, which costs a request but no rate limit when unchanged.
+    /// `KIT_CACHE_TTL` overrides it.
+    pub ttl: u64,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            enabled: true,
+            ttl: 60,
+        }
+    }
 }
 
 fn config_path() -> Result<PathBuf> {
+    if let Some(path) = CONFIG_PATH.get() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        return Ok(path.clone());
+    }
+
     let mut path = dirs::config_dir().ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
     path.push("kit");
     fs::create_dir_all(&path)?;
@@ -22,6 +174,49 @@ fn config_path() -> Result<PathBuf> {
     Ok(path)
 }
 
+/// Overrides the default `<config dir>/kit/config.json`. Must be called
+/// before anything reads the config.
+pub fn set_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+fn load_config() -> Result<Config> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+pub fn endpoints() -> &'static Endpoints {
+    ENDPOINTS.get_or_init(|| {
+        let overrides = load_config().map(|c| c.endpoints).unwrap_or_default();
+        let resolve = |var: &str, configured: Option<String>, default: &str| {
+            env::var(var)
+                .ok()
+                .filter(|url| !url.is_empty())
+                .or(configured)
+                .unwrap_or_else(|| default.to_string())
+                .trim_end_matches('/')
+                .to_string()
+        };
+
+        Endpoints {
+            github_api: resolve("KIT_GITHUB_API_URL", overrides.github_api, DEFAULT_GITHUB_API_URL),
+            github_web: resolve("KIT_GITHUB_URL", overrides.github_web, DEFAULT_GITHUB_WEB_URL),
+            openai_api: resolve("KIT_OPENAI_API_URL", overrides.openai_api, DEFAULT_OPENAI_API_URL),
+            ip_trace: resolve("KIT_IP_TRACE_URL", overrides.ip_trace, DEFAULT_IP_TRACE_URL),
+            ip_trace_v6: resolve("KIT_IP_TRACE_V6_URL", overrides.ip_trace_v6, DEFAULT_IP_TRACE_V6_URL),
+        }
+    })
+}
+
+#[cfg(test)]
+pub fn set_endpoints(endpoints: Endpoints) {
+    let _ = ENDPOINTS.set(endpoints);
+}
+
 pub fn save_credentials(token: &str, username: &str) -> Result<()> {
     let path = config_path()?;
     let mut config = if path.exists() {
@@ -46,6 +241,26 @@ pub fn load_token() -> Result<String> {
     Ok(config.github_token)
 }
 
+pub fn load_github_app() -> Result<Option<GithubAppConfig>> {
+    Ok(load_config()?.github_app)
+}
+
+pub fn load_oauth_scopes() -> Result<Vec<String>> {
+    Ok(load_config()?.oauth_scopes)
+}
+
+pub fn load_ip_fallback_url() -> Result<Option<String>> {
+    Ok(load_config()?.ip_fallback_url)
+}
+
+pub fn load_git_credential_helper() -> Result<bool> {
+    Ok(load_config()?.git_credential_helper)
+}
+
+pub fn load_aliases() -> Result<BTreeMap<String, String>> {
+    Ok(load_config()?.aliases)
+}
+
 pub fn load_username() -> Result<String> {
     let path = config_path()?;
     let contents = fs::read_to_string(path)?;
@@ -81,3 +296,29 @@ pub fn save_openai_api_key(api_key: &str) -> Result<()> {
     fs::write(path, config_json)?;
     Ok(())
 }
+
+pub fn load_ai_settings() -> Result<AiSettings> {
+    let settings = load_config()?.ai;
+    for (name, value) in [("suggestions", settings.suggestions), ("max_regenerations", settings.max_regenerations)] {
+        if value == 0 {
+            return Err(anyhow::anyhow!("ai.{} in {} must be at least 1", name, config_path()?.display()));
+        }
+    }
+    Ok(settings)
+}
+
+pub fn load_cache_settings() -> Result<CacheSettings> {
+    let mut settings = load_config()?.cache;
+    if let Some(ttl) = env::var("KIT_CACHE_TTL").ok().and_then(|ttl| ttl.parse().ok()) {
+        settings.ttl = ttl;
+    }
+    Ok(settings)
+}
+
+pub fn load_network_settings() -> Result<NetworkSettings> {
+    let mut settings = load_config()?.network;
+    if let Some(path) = env::var_os("KIT_CA_BUNDLE").filter(|path| !path.is_empty()) {
+        settings.ca_bundle = Some(PathBuf::from(path));
+    }
+    Ok(settings)
+}
@@ -0,0 +1,250 @@
+//! Commands users add without touching kit: aliases from the `aliases` table
+//! in `config.json`, and git-style `kit-<name>` executables found on PATH.
+//!
+//! Both are registered as extra subcommands so they show up in `kit --help`
+//! and in completions next to the built-in ones. Built-in names always win.
+
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use clap::{Arg, Command};
+use log::debug;
+
+const ARGS_ID: &str = "args";
+const EXTERNAL_PREFIX: &str = "kit-";
+
+pub enum Extension {
+    /// Expands to other kit arguments, split on whitespace.
+    Alias { name: String, args: Vec<String> },
+    /// A `!`-prefixed alias, run by `sh` with the remaining arguments.
+    ShellAlias { name: String, script: String },
+    /// A `kit-<name>` executable on PATH.
+    External { name: String, path: PathBuf },
+}
+
+/// What running an extension amounts to.
+pub enum Invocation {
+    /// Parse these arguments as a regular kit command line instead.
+    Kit(Vec<OsString>),
+    Process(process::Command),
+}
+
+impl Extension {
+    pub fn name(&self) -> &str {
+        match self {
+            Extension::Alias { name, .. } | Extension::ShellAlias { name, .. } | Extension::External { name, .. } => name,
+        }
+    }
+
+    fn about(&self) -> String {
+        match self {
+            Extension::Alias { args, .. } => format!("Alias for `kit {}`", args.join(" ")),
+            Extension::ShellAlias { script, .. } => format!("Alias for `{}`", script),
+            Extension::External { path, .. } => format!("External command ({})", path.display()),
+        }
+    }
+
+    /// Builds the invocation for the full kit command line `argv`, in which
+    /// this extension's name is the subcommand.
+    pub fn invoke(&self, argv: &[OsString]) -> Invocation {
+        let position = argv
+            .iter()
+            .skip(1)
+            .position(|arg| arg.as_os_str() == OsStr::new(self.name()))
+            .map_or(argv.len(), |index| index + 1);
+        let (before, after) = argv.split_at(position);
+        let rest = after.get(1..).unwrap_or_default();
+
+        match self {
+            // Global flags given before the alias still apply.
+            Extension::Alias { args, .. } => Invocation::Kit(
+                before
+                    .iter()
+                    .cloned()
+                    .chain(args.iter().map(OsString::from))
+                    .chain(rest.iter().cloned())
+                    .collect(),
+            ),
+            Extension::ShellAlias { name, script } => {
+                let mut command = process::Command::new("sh");
+                command.arg("-c").arg(format!("{} \"$@\"", script)).arg(name).args(rest);
+                Invocation::Process(command)
+            }
+            Extension::External { path, .. } => {
+                let mut command = process::Command::new(path);
+                command.args(rest);
+                Invocation::Process(command)
+            }
+        }
+    }
+}
+
+/// Collects `aliases` and PATH executables, skipping anything that would
+/// shadow one of `builtins`' subcommands. Scanning PATH reads every directory
+/// on it, so callers only do this when an extension may be involved.
+pub fn discover(builtins: &Command, aliases: BTreeMap<String, String>) -> Vec<Extension> {
+    let mut taken = builtin_names(builtins);
+    let externals = env::var_os("PATH")
+        .map(|path| find_externals(&path))
+        .unwrap_or_default();
+
+    let candidates = aliases.into_iter().map(|(name, expansion)| parse_alias(name, &expansion)).chain(externals);
+
+    let mut extensions = Vec::new();
+    for extension in candidates {
+        if taken.insert(extension.name().to_string()) {
+            extensions.push(extension);
+        } else {
+            debug!("Ignoring {}, the name is already taken", extension.name());
+        }
+    }
+    extensions
+}
+
+fn builtin_names(builtins: &Command) -> HashSet<String> {
+    builtins
+        .get_subcommands()
+        .flat_map(|command| std::iter::once(command.get_name()).chain(command.get_all_aliases()))
+        .chain(["help"])
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether any of `words` is one of `builtins`' subcommands, in which case
+/// the command line can't be about an extension.
+pub fn names_builtin(builtins: &Command, words: &[OsString]) -> bool {
+    let names = builtin_names(builtins);
+    words.iter().any(|word| word.to_str().is_some_and(|word| names.contains(word)))
+}
+
+fn parse_alias(name: String, expansion: &str) -> Extension {
+    match expansion.strip_prefix('!') {
+        Some(script) => Extension::ShellAlias { name, script: script.trim().to_string() },
+        None => Extension::Alias { name, args: expansion.split_whitespace().map(str::to_string).collect() },
+    }
+}
+
+/// Executables named `kit-<name>` in the directories of `path_var`; earlier
+/// directories take precedence, like they do for the shell.
+fn find_externals(path_var: &OsStr) -> Vec<Extension> {
+    let mut seen = HashSet::new();
+    let mut externals = Vec::new();
+
+    for dir in env::split_paths(path_var) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<(String, PathBuf)> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().into_string().ok()?;
+                let name = file_name.strip_prefix(EXTERNAL_PREFIX)?;
+                let name = name.strip_suffix(env::consts::EXE_SUFFIX).unwrap_or(name);
+                (!name.is_empty() && is_executable(&entry.path())).then(|| (name.to_string(), entry.path()))
+            })
+            .collect();
+        found.sort();
+
+        for (name, path) in found {
+            if seen.insert(name.clone()) {
+                externals.push(Extension::External { name, path });
+            }
+        }
+    }
+    externals
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+/// Adds `extensions` to `command` as subcommands that accept any arguments.
+pub fn register(mut command: Command, extensions: &[Extension]) -> Command {
+    for extension in extensions {
+        command = command.subcommand(
+            Command::new(extension.name().to_string())
+                .about(extension.about())
+                .disable_help_flag(true)
+                .arg(
+                    Arg::new(ARGS_ID)
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true)
+                        .value_parser(clap::value_parser!(OsString)),
+                ),
+        );
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn alias_expands_in_place_of_its_name() {
+        let alias = parse_alias("myip".to_string(), "ip -4 --copy");
+        let Invocation::Kit(expanded) = alias.invoke(&argv(&["kit", "-v", "myip", "--local"])) else {
+            panic!("expected a kit invocation");
+        };
+        assert_eq!(expanded, argv(&["kit", "-v", "ip", "-4", "--copy", "--local"]));
+
+        let shell = parse_alias("prs".to_string(), "!gh pr list");
+        let Invocation::Process(command) = shell.invoke(&argv(&["kit", "prs", "--draft"])) else {
+            panic!("expected a process invocation");
+        };
+        let args: Vec<&OsStr> = command.get_args().collect();
+        assert_eq!(args, vec!["-c", "gh pr list \"$@\"", "prs", "--draft"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn find_externals_lists_executables_on_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = env::temp_dir().join(format!("kit-ext-a-{}", process::id()));
+        let second = env::temp_dir().join(format!("kit-ext-b-{}", process::id()));
+        for (dir, name, mode) in [
+            (&first, "kit-hello", 0o755),
+            (&first, "kit-notes", 0o644),
+            (&second, "kit-hello", 0o755),
+            (&second, "kit-deploy", 0o755),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+            let path = dir.join(name);
+            fs::write(&path, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+
+        let path_var = env::join_paths([&first, &second]).unwrap();
+        let found: Vec<(String, PathBuf)> = find_externals(&path_var)
+            .into_iter()
+            .map(|extension| match extension {
+                Extension::External { name, path } => (name, path),
+                _ => panic!("expected an external command"),
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("hello".to_string(), first.join("kit-hello")),
+                ("deploy".to_string(), second.join("kit-deploy")),
+            ]
+        );
+    }
+}
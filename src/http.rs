@@ -1,14 +1,291 @@
-use reqwest::blocking::Client;
+use anyhow::Result;
+use log::{debug, error, warn};
+use reqwest::blocking::{Client, ClientBuilder, Request, RequestBuilder, Response};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Certificate, Method, Proxy, StatusCode};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::net::IpAddr;
 use std::sync::OnceLock;
 use std::time::Duration;
+use std::{fs, thread};
 
+use crate::config::{self, NetworkSettings};
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+// A server asking for a longer pause than this is better reported than waited on.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static SETTINGS: OnceLock<NetworkSettings> = OnceLock::new();
 static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+static AI_CLIENT: OnceLock<Client> = OnceLock::new();
+static STREAMING_CLIENT: OnceLock<Client> = OnceLock::new();
+static UPLOAD_CLIENT: OnceLock<Client> = OnceLock::new();
+
+fn settings() -> &'static NetworkSettings {
+    SETTINGS.get_or_init(|| {
+        config::load_network_settings().unwrap_or_else(|e| {
+            warn!("Could not load network settings, using defaults: {}", e);
+            NetworkSettings::default()
+        })
+    })
+}
 
+/// Client for the GitHub API.
 pub fn get_client() -> &'static Client {
     HTTP_CLIENT.get_or_init(|| {
-        Client::builder()
-            .timeout(Duration::from_secs(10))
+        builder()
+            .timeout(Duration::from_secs(settings().timeouts.github))
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
+
+/// Client for the AI provider, whose streamed completions need a more
+/// generous timeout than the GitHub API.
+pub fn get_ai_client() -> &'static Client {
+    AI_CLIENT.get_or_init(|| {
+        builder()
+            .timeout(Duration::from_secs(settings().timeouts.openai))
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
+
+// Downloads can legitimately take longer than any fixed timeout, so only
+// connecting and each read are bounded here: once the headers are in, the
+// blocking client applies its timeout to every read of the response body,
+// which catches a stalled stream without limiting how long a healthy one runs.
+pub fn get_streaming_client() -> &'static Client {
+    STREAMING_CLIENT.get_or_init(|| {
+        builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(STREAM_IDLE_TIMEOUT)
             .build()
             .expect("Failed to create HTTP client")
     })
 }
+
+// Sending a request body and waiting for the response headers fall under a
+// single timeout in the blocking client, so any timeout here would cap the
+// whole upload. Only connecting is bounded.
+pub fn get_upload_client() -> &'static Client {
+    UPLOAD_CLIENT.get_or_init(|| {
+        builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(None)
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
+
+pub fn get_client_bound_to(local_address: IpAddr) -> Result<Client> {
+    Ok(builder()
+        .timeout(Duration::from_secs(settings().timeouts.ip))
+        .local_address(local_address)
+        .build()?)
+}
+
+/// A client builder with the configured proxy and extra root certificates.
+/// Problems with either are logged rather than fatal, so a broken setting
+/// surfaces as a connection error on the request that needs it.
+pub fn builder() -> ClientBuilder {
+    let settings = settings();
+    let mut builder = Client::builder();
+
+    if let Some(proxy) = &settings.proxy {
+        match Proxy::all(proxy) {
+            Ok(proxy) => builder = builder.proxy(proxy),
+            Err(e) => error!("Ignoring invalid proxy {}: {}", proxy, e),
+        }
+    }
+
+    if let Some(path) = &settings.ca_bundle {
+        let certificates = fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|pem| Ok(Certificate::from_pem_bundle(&pem)?));
+        match certificates {
+            Ok(certificates) => {
+                for certificate in certificates {
+                    builder = builder.add_root_certificate(certificate);
+                }
+            }
+            Err(e) => error!("Could not load CA bundle {}: {}", path.display(), e),
+        }
+    }
+
+    builder
+}
+
+pub trait SendWithRetry {
+    /// Sends the request, retrying transient failures with exponential
+    /// backoff. Requests that may have had side effects are only retried
+    /// when the server explicitly rejected them (429) or they never got
+    /// through (connection errors).
+    fn send_with_retry(self) -> Result<Response>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    fn send_with_retry(self) -> Result<Response> {
+        let (client, request) = self.build_split();
+        execute_with_retry(&client, request?)
+    }
+}
+
+pub fn execute_with_retry(client: &Client, request: Request) -> Result<Response> {
+    let idempotent = is_idempotent(request.method());
+    let max_retries = settings().max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
+
+    let mut attempt = 0;
+    loop {
+        // Streaming bodies can't be cloned; those get a single attempt.
+        let Some(retryable) = request.try_clone().filter(|_| attempt < max_retries) else {
+            return Ok(client.execute(request)?);
+        };
+
+        let delay = match client.execute(retryable) {
+            Ok(response) => match retry_delay(&response, idempotent, attempt) {
+                Some(delay) => {
+                    warn!("{} returned {}, retrying in {:.1}s", response.url(), response.status(), delay.as_secs_f32());
+                    delay
+                }
+                None => return Ok(response),
+            },
+            Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                let delay = backoff(attempt);
+                warn!("Request failed ({}), retrying in {:.1}s", e, delay.as_secs_f32());
+                delay
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        thread::sleep(delay);
+        attempt += 1;
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS)
+}
+
+fn retry_delay(response: &Response, idempotent: bool, attempt: u32) -> Option<Duration> {
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+
+    let retryable = match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT => idempotent,
+        // GitHub's secondary rate limits come back as 403 with Retry-After.
+        StatusCode::FORBIDDEN => retry_after.is_some(),
+        _ => false,
+    };
+    if !retryable {
+        return None;
+    }
+
+    match retry_after {
+        Some(delay) if delay > MAX_RETRY_AFTER => {
+            debug!("Not waiting {}s as asked by Retry-After", delay.as_secs());
+            None
+        }
+        Some(delay) => Some(delay),
+        None => Some(backoff(attempt)),
+    }
+}
+
+// Only the delay-seconds form; an HTTP date falls back to the regular backoff.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// Exponential backoff with jitter: somewhere between half and all of
+/// `BASE_BACKOFF * 2^attempt`, so clients that failed together don't retry
+/// together.
+fn backoff(attempt: u32) -> Duration {
+    let ceiling = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+
+    let mut bytes = [0u8; 4];
+    let fraction = match SystemRandom::new().fill(&mut bytes) {
+        Ok(()) => u32::from_le_bytes(bytes) as f64 / u32::MAX as f64,
+        Err(_) => 1.0,
+    };
+    ceiling.mul_f64(0.5 + fraction / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    use reqwest::blocking::Body;
+
+    use crate::test_support;
+
+    #[test]
+    fn send_with_retry_retries_idempotent_requests_on_bad_gateway() {
+        let server = test_support::server();
+        server.route("GET", "/api/retry/get", &["http/bad_gateway", "github/user"], None);
+        server.route("POST", "/api/retry/post", &["http/bad_gateway", "github/user"], None);
+
+        let url = format!("{}/api/retry/get", server.base_url);
+        let response = get_client().get(&url).send_with_retry().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(server.requests_to("/api/retry/get").len(), 2);
+
+        let url = format!("{}/api/retry/post", server.base_url);
+        let response = get_client().post(&url).body("{}").send_with_retry().unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(server.requests_to("/api/retry/post").len(), 1);
+    }
+
+    // Hands out one byte per read, slowly.
+    struct SlowReader {
+        left: usize,
+    }
+
+    impl io::Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.left == 0 || buf.is_empty() {
+                return Ok(0);
+            }
+            thread::sleep(Duration::from_millis(300));
+            self.left -= 1;
+            buf[0] = b'x';
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn uploads_are_not_cut_off_by_a_timeout() {
+        let server = test_support::server();
+        server.route("POST", "/api/uploads/slow", &["github/release_asset_uploaded"], None);
+        let url = format!("{}/api/uploads/slow", server.base_url);
+        let slow_body = || Body::sized(SlowReader { left: 4 }, 4);
+
+        let limited = builder().timeout(Duration::from_millis(500)).build().unwrap();
+        assert!(limited.post(&url).body(slow_body()).send().is_err());
+
+        let response = get_upload_client().post(&url).body(slow_body()).send().unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(server.requests_to("/api/uploads/slow").last().unwrap().body, "xxxx");
+    }
+
+    #[test]
+    fn backoff_grows_with_jitter_up_to_the_cap() {
+        for attempt in 0..8 {
+            let ceiling = BASE_BACKOFF.saturating_mul(2u32.pow(attempt)).min(MAX_BACKOFF);
+            let delay = backoff(attempt);
+            assert!(delay >= ceiling / 2 && delay <= ceiling, "attempt {}: {:?}", attempt, delay);
+        }
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+    }
+}
@@ -1,8 +1,15 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use env_logger::Builder;
-use log::LevelFilter;
-use std::io::Write;
+use clap::error::ErrorKind;
+use clap::{Args, ColorChoice, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::{ArgValueCompleter, CompleteEnv, Shell};
+use env_logger::{Builder, WriteStyle};
+use log::{error, info, warn, LevelFilter};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process;
 use colored::*;
 
 mod config;
@@ -10,6 +17,12 @@ mod auth;
 mod commands;
 mod utils;
 mod http;
+mod output;
+mod cache;
+mod completions;
+mod extensions;
+#[cfg(test)]
+mod test_support;
 
 #[derive(Parser)]
 #[command(name = "kit")]
@@ -17,44 +30,890 @@ mod http;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    globals: GlobalArgs,
+}
+
+#[derive(Args)]
+struct GlobalArgs {
+    #[arg(long, global = true, value_enum, default_value_t = output::OutputFormat::Text, help = "Output format; markdown only differs from text for ai-explain")]
+    output: output::OutputFormat,
+
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, help = "Increase log verbosity (-v debug, -vv trace)")]
+    verbose: u8,
+
+    #[arg(short, long, global = true, conflicts_with = "verbose", help = "Only show errors")]
+    quiet: bool,
+
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto, help = "When to use colors")]
+    color: ColorChoice,
+
+    #[arg(long, global = true, value_name = "PATH", help = "Use a different config file")]
+    config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Clone a repository, adding the parent as upstream for your forks
     Clone {
+        #[arg(help = "Repository URL or owner/repo", add = ArgValueCompleter::new(completions::complete_repos))]
         repo: String,
+        #[arg(short, long, help = "Branch to check out instead of the default", add = ArgValueCompleter::new(completions::complete_branches))]
+        branch: Option<String>,
     },
 
+    /// Fork a repository (coming soon)
     Fork {
+        #[arg(help = "Repository URL or owner/repo", add = ArgValueCompleter::new(completions::complete_repos))]
         repo: String,
     },
+    /// Stage everything and commit with an AI-polished message
     AiCommit {
         message: String,
+        #[arg(short = 'n', long, help = "Number of suggestions to generate")]
+        count: Option<usize>,
+        #[arg(short, long, help = "Model to use instead of the configured one")]
+        model: Option<String>,
+        #[arg(short, long, help = "Sampling temperature")]
+        temperature: Option<f32>,
+    },
+    /// Turn a request into a git or shell command with AI, and run it
+    AiAsk {
+        #[arg(help = "What you want to do, e.g. \"undo my last commit but keep changes\"")]
+        request: String,
+        #[arg(short, long, help = "Model to use instead of the configured one")]
+        model: Option<String>,
     },
+    /// Explain a commit, a range of commits or a file with AI
+    AiExplain {
+        #[arg(help = "Revision (HEAD~2), range (main..HEAD) or file, optionally with :line or :start-end")]
+        target: String,
+        #[arg(short, long, help = "Model to use instead of the configured one")]
+        model: Option<String>,
+    },
+    /// Review staged changes, a commit range or a pull request with AI
+    AiReview {
+        #[arg(conflicts_with = "pr", help = "Commit range to review, e.g. main..HEAD, instead of the staged changes")]
+        range: Option<String>,
+        #[arg(long, value_name = "NUMBER", help = "Review a pull request")]
+        pr: Option<u64>,
+        #[arg(short = 'R', long, requires = "pr", help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long, requires = "pr", help = "Post the issues as review comments on the pull request")]
+        post: bool,
+        #[arg(short, long, help = "Model to use instead of the configured one")]
+        model: Option<String>,
+        #[arg(short, long, help = "Don't ask before posting")]
+        yes: bool,
+    },
+    /// Show your public IP addresses
     Ip {
         #[arg(short, long, help = "Copy IP to clipboard")]
         copy: bool,
+        #[arg(short = '4', long = "ipv4", conflicts_with = "ipv6_only", help = "Only look up the IPv4 address")]
+        ipv4_only: bool,
+        #[arg(short = '6', long = "ipv6", help = "Only look up the IPv6 address")]
+        ipv6_only: bool,
+        #[arg(short, long, help = "Also list local interface addresses")]
+        local: bool,
+    },
+    /// Open the current repository, branch, file or line on GitHub
+    Browse {
+        #[arg(value_name = "PATH[:LINE]", help = "File or directory to open, optionally with a line number")]
+        target: Option<String>,
+        #[arg(short, long, help = "Print the URL instead of opening a browser")]
+        no_browser: bool,
+    },
+    /// Create, inspect and administer GitHub repositories
+    Repo {
+        #[command(subcommand)]
+        command: RepoCommands,
+    },
+    /// List, watch and re-run GitHub Actions workflow runs
+    Run {
+        #[command(subcommand)]
+        command: RunCommands,
+    },
+    /// Trigger GitHub Actions workflows
+    Workflow {
+        #[command(subcommand)]
+        command: WorkflowCommands,
+    },
+    /// Create, download and manage GitHub releases
+    Release {
+        #[command(subcommand)]
+        command: ReleaseCommands,
+    },
+    /// Share files and snippets as GitHub gists
+    Gist {
+        #[command(subcommand)]
+        command: GistCommands,
+    },
+    /// Manage GitHub authentication
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommands,
+    },
+    /// Network diagnostics
+    Net {
+        #[command(subcommand)]
+        command: NetCommands,
+    },
+    /// Inspect or clear cached GitHub API responses
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Update kit to the latest release
+    SelfUpdate {
+        #[arg(long, help = "Only report whether an update is available")]
+        check: bool,
+    },
+    /// Print a shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Generate roff man pages
+    Man {
+        #[arg(long, value_name = "DIR", help = "Write a page for every subcommand into DIR instead of printing kit(1)")]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RepoCommands {
+    /// Show a repository's description, stars, topics and open issues and PRs
+    View {
+        #[arg(help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Create a repository from the current directory and push it
+    Create {
+        #[arg(help = "Repository name, defaults to the current directory's name")]
+        name: Option<String>,
+        #[arg(long, help = "Create the repository in this organization instead of your account")]
+        org: Option<String>,
+        #[arg(long, help = "Make the repository private")]
+        private: bool,
+        #[arg(short, long, help = "Repository description")]
+        description: Option<String>,
+        #[arg(long, value_name = "KEYWORD", help = "License template to start with, e.g. mit or apache-2.0")]
+        license: Option<String>,
+        #[arg(long, value_name = "TEMPLATE", help = "gitignore template to start with, e.g. Rust or Node")]
+        gitignore: Option<String>,
+    },
+    /// Change a repository's description, homepage, visibility, default branch or topics
+    Edit {
+        #[arg(help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "New description")]
+        description: Option<String>,
+        #[arg(long, help = "New homepage URL")]
+        homepage: Option<String>,
+        #[arg(long, value_parser = ["public", "private"], help = "Make the repository public or private")]
+        visibility: Option<String>,
+        #[arg(long, help = "Branch to make the default")]
+        default_branch: Option<String>,
+        #[arg(long = "add-topic", value_name = "TOPIC", value_delimiter = ',', help = "Topics to add")]
+        add_topics: Vec<String>,
+        #[arg(long = "remove-topic", value_name = "TOPIC", value_delimiter = ',', help = "Topics to remove")]
+        remove_topics: Vec<String>,
+        #[arg(short, long, help = "Don't ask before changing visibility")]
+        yes: bool,
+    },
+    /// Archive a repository, making it read-only
+    Archive {
+        #[arg(help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Delete a repository
+    Delete {
+        #[arg(help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Rename a repository and update the origin remote
+    Rename {
+        new_name: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Transfer a repository to another user or organization
+    Transfer {
+        new_owner: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Set the protection rules of a branch, replacing any existing ones
+    Protect {
+        branch: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long, value_name = "COUNT", help = "Require pull requests with this many approving reviews")]
+        reviews: Option<u8>,
+        #[arg(long, requires = "reviews", help = "Dismiss approvals when new commits are pushed")]
+        dismiss_stale: bool,
+        #[arg(long, requires = "reviews", help = "Require a review from code owners")]
+        code_owners: bool,
+        #[arg(long = "status-check", value_name = "CONTEXT", value_delimiter = ',', help = "Status checks that must pass")]
+        status_checks: Vec<String>,
+        #[arg(long, requires = "status_checks", help = "Require branches to be up to date before merging")]
+        strict: bool,
+        #[arg(long, help = "Apply the rules to administrators too")]
+        enforce_admins: bool,
+        #[arg(long, help = "Allow force pushes")]
+        allow_force_pushes: bool,
+        #[arg(long, help = "Allow the branch to be deleted")]
+        allow_deletions: bool,
+        #[arg(short, long, help = "Don't ask before replacing existing rules")]
+        yes: bool,
+    },
+    /// Remove all protection rules from a branch
+    Unprotect {
+        branch: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Manage collaborators
+    Collaborator {
+        #[command(subcommand)]
+        command: CollaboratorCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum CollaboratorCommands {
+    /// List collaborators and their roles
+    List {
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Invite a user or change their permission
+    Add {
+        username: String,
+        #[arg(short, long, default_value = "push", value_parser = ["pull", "triage", "push", "maintain", "admin"], help = "Permission to grant")]
+        permission: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Remove a collaborator
+    Remove {
+        username: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum RunCommands {
+    /// List recent runs of the current branch
+    List {
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Branch to list runs of instead of the current one")]
+        branch: Option<String>,
+        #[arg(short, long, conflicts_with = "branch", help = "List runs of all branches")]
+        all: bool,
+        #[arg(short, long, help = "Only runs of this workflow file, e.g. ci.yml")]
+        workflow: Option<String>,
+        #[arg(short = 'L', long, default_value_t = 20, value_parser = clap::value_parser!(u8).range(1..=100), help = "Maximum number of runs")]
+        limit: u8,
+    },
+    /// Show a run's jobs and the steps that failed
+    View {
+        run_id: u64,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Follow a run until it completes, exiting non-zero unless it succeeds
+    Watch {
+        #[arg(help = "Run to watch, defaults to the latest run of the current branch")]
+        run_id: Option<u64>,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, default_value_t = 3, help = "Seconds between updates")]
+        interval: u64,
+    },
+    /// Re-run a run
+    Rerun {
+        run_id: u64,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long, help = "Only re-run failed jobs and their dependents")]
+        failed: bool,
+    },
+    /// Cancel a run
+    Cancel {
+        run_id: u64,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Print job logs
+    Logs {
+        run_id: u64,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Only this job, by name or ID")]
+        job: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkflowCommands {
+    /// Dispatch a workflow that has a workflow_dispatch trigger
+    Run {
+        #[arg(help = "Workflow file, e.g. deploy.yml or .github/workflows/deploy.yml")]
+        workflow: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long = "ref", value_name = "REF", help = "Branch or tag to run on, defaults to the current branch")]
+        git_ref: Option<String>,
+        #[arg(short = 'f', long = "field", value_name = "KEY=VALUE", help = "Workflow input, repeatable")]
+        fields: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ReleaseCommands {
+    /// Create a release, tagging the target branch if the tag doesn't exist
+    Create {
+        tag: String,
+        #[arg(help = "Files to attach to the release")]
+        assets: Vec<PathBuf>,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long, help = "Branch or commit to tag, defaults to the default branch")]
+        target: Option<String>,
+        #[arg(long, help = "Release title, defaults to the tag")]
+        title: Option<String>,
+        #[arg(short, long, conflicts_with_all = ["notes_file", "generate_notes"], help = "Release notes")]
+        notes: Option<String>,
+        #[arg(short = 'F', long, conflicts_with = "generate_notes", help = "Read release notes from a file")]
+        notes_file: Option<PathBuf>,
+        #[arg(short, long, help = "Write release notes from the commits since the last tag with AI")]
+        generate_notes: bool,
+        #[arg(short, long, help = "Save as a draft instead of publishing")]
+        draft: bool,
+        #[arg(short, long, help = "Mark as a pre-release")]
+        prerelease: bool,
+        #[arg(short, long, help = "Don't ask before using generated notes")]
+        yes: bool,
+    },
+    /// List releases, newest first
+    List {
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short = 'L', long, default_value_t = 30, value_parser = clap::value_parser!(u8).range(1..=100), help = "Maximum number of releases")]
+        limit: u8,
+    },
+    /// Show a release's notes and assets
+    View {
+        #[arg(help = "Release tag, defaults to the latest release")]
+        tag: Option<String>,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+    },
+    /// Attach files to a release
+    Upload {
+        tag: String,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long, help = "Replace assets with the same name")]
+        clobber: bool,
+    },
+    /// Download release assets, by default those for this platform
+    Download {
+        #[arg(help = "Release tag, defaults to the latest release")]
+        tag: Option<String>,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(short, long = "pattern", value_name = "GLOB", help = "Only assets matching this pattern, repeatable")]
+        patterns: Vec<String>,
+        #[arg(short = 'D', long, default_value = ".", help = "Directory to download into")]
+        dir: PathBuf,
+    },
+    /// Delete a release
+    Delete {
+        tag: String,
+        #[arg(short = 'R', long, help = "Repository URL or owner/repo, defaults to the origin remote", add = ArgValueCompleter::new(completions::complete_repos))]
+        repo: Option<String>,
+        #[arg(long, help = "Also delete the tag")]
+        cleanup_tag: bool,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum GistCommands {
+    /// Create a gist and copy its URL to the clipboard
+    Create {
+        #[arg(required_unless_present = "clipboard", help = "Files to put in the gist")]
+        files: Vec<PathBuf>,
+        #[arg(short, long, help = "Use the clipboard contents as a file")]
+        clipboard: bool,
+        #[arg(short, long, requires = "clipboard", help = "Name of the clipboard file [default: clipboard.txt]")]
+        filename: Option<String>,
+        #[arg(short, long)]
+        description: Option<String>,
+        #[arg(short, long, help = "Make the gist public instead of secret")]
+        public: bool,
+    },
+    /// List your gists
+    List {
+        #[arg(short = 'L', long, default_value_t = 30, value_parser = clap::value_parser!(u8).range(1..=100), help = "Maximum number of gists")]
+        limit: u8,
+    },
+    /// Print the files of a gist
+    View {
+        #[arg(help = "Gist ID or URL")]
+        gist: String,
+        #[arg(short, long, help = "Only this file")]
+        filename: Option<String>,
+    },
+    /// Change a gist, or edit one of its files in $EDITOR
+    Edit {
+        #[arg(help = "Gist ID or URL")]
+        gist: String,
+        #[arg(help = "Files to add or replace, matched by name")]
+        files: Vec<PathBuf>,
+        #[arg(short, long, help = "File to open in the editor")]
+        filename: Option<String>,
+        #[arg(short, long, value_name = "FILENAME", help = "File to remove, repeatable")]
+        remove: Vec<String>,
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Delete a gist
+    Delete {
+        #[arg(help = "Gist ID or URL")]
+        gist: String,
+        #[arg(short, long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum NetCommands {
+    /// Resolve a hostname
+    Dns {
+        host: String,
+    },
+    /// Check that a TCP port accepts connections
+    Port {
+        host: String,
+        port: u16,
+        #[arg(short, long, default_value_t = 5, help = "Timeout in seconds")]
+        timeout: u64,
+    },
+    /// Measure HTTP latency and inspect the TLS certificate
+    Http {
+        url: String,
+    },
+    /// Check connectivity to GitHub and the AI provider
+    Doctor,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show how much is cached and where
+    Stats,
+    /// Delete all cached responses
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum AuthCommands {
+    /// Log in with the GitHub device flow or a personal access token
+    Login {
+        #[arg(long, help = "Print the verification URL instead of opening a browser")]
+        no_browser: bool,
+        #[arg(long, conflicts_with_all = ["no_browser", "scopes"], help = "Read a personal access token from stdin; its scopes are set when creating it")]
+        with_token: bool,
+        #[arg(short, long, value_delimiter = ',', help = "Additional OAuth scopes to request, e.g. repo,workflow,read:org")]
+        scopes: Vec<String>,
+    },
+}
+
+fn main() {
+    // Answers the shell's completion requests and exits when COMPLETE is set.
+    CompleteEnv::with_factory(command_with_extensions)
+        .var(completions::COMPLETE_ENV)
+        .complete();
+
+    let argv: Vec<OsString> = env::args_os().collect();
+    // Aliases live in the config file, so --config has to be known before
+    // the full command line can be parsed.
+    if let Some(path) = config_flag(&argv) {
+        config::set_config_path(path);
+    }
+
+    // Extensions are only looked for when the command line doesn't parse as
+    // a built-in command, or to list them in the help.
+    let (cli, aliases) = match Cli::try_parse_from(&argv) {
+        Ok(cli) => (cli, Ok(Default::default())),
+        Err(e) if may_name_extension(e.kind()) => parse_with_extensions(&argv),
+        Err(e) => e.exit(),
+    };
+    init(&cli.globals);
+    report_alias_error(&aliases);
+
+    if let Err(e) = run(&cli) {
+        output::emit_error(&e);
+        error!("{:#}", e);
+        process::exit(1);
+    }
+}
+
+fn may_name_extension(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::InvalidSubcommand
+            | ErrorKind::DisplayHelp
+            | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+            | ErrorKind::MissingSubcommand
+    )
+}
+
+/// Parses `argv` with aliases and external commands added, running an
+/// external command or shell alias right away. Also returns the aliases, so
+/// a broken config can be reported once logging is set up.
+fn parse_with_extensions(argv: &[OsString]) -> (Cli, Result<BTreeMap<String, String>>) {
+    let aliases = config::load_aliases();
+    let extensions = extensions::discover(&Cli::command(), aliases.as_ref().cloned().unwrap_or_default());
+    let matches = extensions::register(Cli::command(), &extensions).get_matches_from(argv);
+    let extension = matches
+        .subcommand_name()
+        .and_then(|name| extensions.iter().find(|extension| extension.name() == name));
+
+    let cli = match extension.map(|extension| extension.invoke(argv)) {
+        Some(extensions::Invocation::Process(command)) => {
+            let globals = GlobalArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+            init(&globals);
+            report_alias_error(&aliases);
+            run_extension(command)
+        }
+        // Expansions are parsed with the built-in commands only, so an alias
+        // can't refer to another alias.
+        Some(extensions::Invocation::Kit(expanded)) => Cli::parse_from(expanded),
+        None => Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit()),
+    };
+    (cli, aliases)
+}
+
+fn report_alias_error(aliases: &Result<BTreeMap<String, String>>) {
+    if let Err(e) = aliases {
+        warn!("Could not load aliases: {:#}", e);
+    }
+}
+
+// Called on every <TAB>. Once a built-in subcommand has been typed the
+// extensions can't matter, so PATH isn't scanned for them.
+fn command_with_extensions() -> clap::Command {
+    let command = Cli::command();
+    // The shell passes `kit -- kit <words>`, the last word being completed.
+    let args: Vec<OsString> = env::args_os().collect();
+    let words = args.iter().position(|arg| arg == "--").map_or(&[][..], |index| &args[index + 1..]);
+    if words.len() > 2 && extensions::names_builtin(&command, &words[1..words.len() - 1]) {
+        return command;
     }
+    let aliases = config::load_aliases().unwrap_or_default();
+    let extensions = extensions::discover(&command, aliases);
+    extensions::register(command, &extensions)
 }
 
-fn main() -> Result<()> {
-    init_logger();
+fn config_flag(argv: &[OsString]) -> Option<PathBuf> {
+    let matches = Cli::command()
+        .ignore_errors(true)
+        .disable_help_flag(true)
+        .disable_help_subcommand(true)
+        .try_get_matches_from(argv)
+        .ok()?;
+    GlobalArgs::from_arg_matches(&matches).ok()?.config
+}
 
-    let cli = Cli::parse();
+fn init(globals: &GlobalArgs) {
+    output::init(globals.output);
+    let colors = init_colors(globals.color);
+    init_logger(globals, colors);
+}
 
+fn run_extension(mut command: process::Command) -> ! {
+    match command.status() {
+        Ok(status) => process::exit(status.code().unwrap_or(1)),
+        Err(e) => {
+            error!("Could not run {}: {}", command.get_program().to_string_lossy(), e);
+            process::exit(1)
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<()> {
     match &cli.command {
-        Commands::Clone { repo } => commands::clone_repository(repo)?,
+        Commands::Clone { repo, branch } => output::emit(&commands::clone_repository(repo, branch.as_deref())?)?,
         Commands::Fork { repo } => commands::fork_repository(repo)?,
-        Commands::Ip { copy } => commands::ip(*copy)?,
-        Commands::AiCommit { message } => commands::commit(message)?,
+        Commands::Ip { copy, ipv4_only, ipv6_only, local } => {
+            let options = commands::IpOptions {
+                copy: *copy,
+                ipv4_only: *ipv4_only,
+                ipv6_only: *ipv6_only,
+                local: *local,
+            };
+            output::emit(&commands::ip(&options)?)?
+        }
+        Commands::AiCommit { message, count, model, temperature } => {
+            let overrides = commands::SuggestionOverrides {
+                model: model.clone(),
+                count: *count,
+                temperature: *temperature,
+            };
+            output::emit(&commands::commit(message, &overrides)?)?
+        }
+        Commands::AiAsk { request, model } => {
+            let result = commands::ai_ask(request, model.as_deref())?;
+            output::emit(&result)?;
+            result.ensure_succeeded()?
+        }
+        Commands::AiExplain { target, model } => output::emit(&commands::ai_explain(target, model.as_deref())?)?,
+        Commands::AiReview { range, pr, repo, post, model, yes } => {
+            let target = match (range, pr) {
+                (_, Some(number)) => commands::ReviewTarget::PullRequest(*number),
+                (Some(range), None) => commands::ReviewTarget::Range(range.clone()),
+                (None, None) => commands::ReviewTarget::Staged,
+            };
+            output::emit(&commands::ai_review(repo.as_deref(), &target, model.as_deref(), *post, *yes)?)?
+        }
+        Commands::Browse { target, no_browser } => output::emit(&commands::browse(target.as_deref(), !no_browser)?)?,
+        Commands::Repo { command } => match command {
+            RepoCommands::View { repo } => output::emit(&commands::repo_view(repo.as_deref())?)?,
+            RepoCommands::Edit { repo, description, homepage, visibility, default_branch, add_topics, remove_topics, yes } => {
+                let options = commands::RepoEditOptions {
+                    description: description.clone(),
+                    homepage: homepage.clone(),
+                    private: visibility.as_deref().map(|visibility| visibility == "private"),
+                    default_branch: default_branch.clone(),
+                    add_topics: add_topics.clone(),
+                    remove_topics: remove_topics.clone(),
+                    yes: *yes,
+                };
+                output::emit(&commands::repo_edit(repo.as_deref(), &options)?)?
+            }
+            RepoCommands::Archive { repo, yes } => output::emit(&commands::repo_archive(repo.as_deref(), *yes)?)?,
+            RepoCommands::Delete { repo, yes } => output::emit(&commands::repo_delete(repo.as_deref(), *yes)?)?,
+            RepoCommands::Rename { new_name, repo } => output::emit(&commands::repo_rename(repo.as_deref(), new_name)?)?,
+            RepoCommands::Transfer { new_owner, repo, yes } => {
+                output::emit(&commands::repo_transfer(repo.as_deref(), new_owner, *yes)?)?
+            }
+            RepoCommands::Protect {
+                branch,
+                repo,
+                reviews,
+                dismiss_stale,
+                code_owners,
+                status_checks,
+                strict,
+                enforce_admins,
+                allow_force_pushes,
+                allow_deletions,
+                yes,
+            } => {
+                let options = commands::ProtectOptions {
+                    required_reviews: *reviews,
+                    dismiss_stale_reviews: *dismiss_stale,
+                    require_code_owner_reviews: *code_owners,
+                    status_checks: status_checks.clone(),
+                    strict: *strict,
+                    enforce_admins: *enforce_admins,
+                    allow_force_pushes: *allow_force_pushes,
+                    allow_deletions: *allow_deletions,
+                    yes: *yes,
+                };
+                output::emit(&commands::repo_protect(repo.as_deref(), branch, &options)?)?
+            }
+            RepoCommands::Unprotect { branch, repo, yes } => {
+                output::emit(&commands::repo_unprotect(repo.as_deref(), branch, *yes)?)?
+            }
+            RepoCommands::Collaborator { command } => match command {
+                CollaboratorCommands::List { repo } => output::emit(&commands::collaborator_list(repo.as_deref())?)?,
+                CollaboratorCommands::Add { username, permission, repo } => {
+                    output::emit(&commands::collaborator_add(repo.as_deref(), username, permission)?)?
+                }
+                CollaboratorCommands::Remove { username, repo, yes } => {
+                    output::emit(&commands::collaborator_remove(repo.as_deref(), username, *yes)?)?
+                }
+            },
+            RepoCommands::Create { name, org, private, description, license, gitignore } => {
+                let options = commands::RepoCreateOptions {
+                    name: name.clone(),
+                    org: org.clone(),
+                    private: *private,
+                    description: description.clone(),
+                    license: license.clone(),
+                    gitignore: gitignore.clone(),
+                };
+                output::emit(&commands::repo_create(&options)?)?
+            }
+        },
+        Commands::Run { command } => match command {
+            RunCommands::List { repo, branch, all, workflow, limit } => {
+                let options = commands::RunListOptions {
+                    branch: branch.clone(),
+                    all_branches: *all,
+                    workflow: workflow.clone(),
+                    limit: *limit,
+                };
+                output::emit(&commands::run_list(repo.as_deref(), &options)?)?
+            }
+            RunCommands::View { run_id, repo } => output::emit(&commands::run_view(repo.as_deref(), *run_id)?)?,
+            RunCommands::Watch { run_id, repo, interval } => {
+                let view = commands::run_watch(repo.as_deref(), *run_id, *interval)?;
+                output::emit(&view)?;
+                view.ensure_succeeded()?
+            }
+            RunCommands::Rerun { run_id, repo, failed } => {
+                output::emit(&commands::run_rerun(repo.as_deref(), *run_id, *failed)?)?
+            }
+            RunCommands::Cancel { run_id, repo } => output::emit(&commands::run_cancel(repo.as_deref(), *run_id)?)?,
+            RunCommands::Logs { run_id, repo, job } => commands::run_logs(repo.as_deref(), *run_id, job.as_deref())?,
+        },
+        Commands::Workflow { command } => match command {
+            WorkflowCommands::Run { workflow, repo, git_ref, fields } => {
+                output::emit(&commands::workflow_run(repo.as_deref(), workflow, git_ref.as_deref(), fields)?)?
+            }
+        },
+        Commands::Release { command } => match command {
+            ReleaseCommands::Create { tag, assets, repo, target, title, notes, notes_file, generate_notes, draft, prerelease, yes } => {
+                let options = commands::ReleaseCreateOptions {
+                    target: target.clone(),
+                    title: title.clone(),
+                    notes: notes.clone(),
+                    notes_file: notes_file.clone(),
+                    generate_notes: *generate_notes,
+                    draft: *draft,
+                    prerelease: *prerelease,
+                    assets: assets.clone(),
+                    yes: *yes,
+                };
+                output::emit(&commands::release_create(repo.as_deref(), tag, &options)?)?
+            }
+            ReleaseCommands::List { repo, limit } => output::emit(&commands::release_list(repo.as_deref(), *limit)?)?,
+            ReleaseCommands::View { tag, repo } => output::emit(&commands::release_view(repo.as_deref(), tag.as_deref())?)?,
+            ReleaseCommands::Upload { tag, files, repo, clobber } => {
+                output::emit(&commands::release_upload(repo.as_deref(), tag, files, *clobber)?)?
+            }
+            ReleaseCommands::Download { tag, repo, patterns, dir } => {
+                output::emit(&commands::release_download(repo.as_deref(), tag.as_deref(), patterns, dir)?)?
+            }
+            ReleaseCommands::Delete { tag, repo, cleanup_tag, yes } => {
+                output::emit(&commands::release_delete(repo.as_deref(), tag, *yes, *cleanup_tag)?)?
+            }
+        },
+        Commands::Gist { command } => match command {
+            GistCommands::Create { files, clipboard, filename, description, public } => {
+                let options = commands::GistCreateOptions {
+                    files: files.clone(),
+                    clipboard: *clipboard,
+                    filename: filename.clone(),
+                    description: description.clone(),
+                    public: *public,
+                };
+                output::emit(&commands::gist_create(&options)?)?
+            }
+            GistCommands::List { limit } => output::emit(&commands::gist_list(*limit)?)?,
+            GistCommands::View { gist, filename } => output::emit(&commands::gist_view(gist, filename.as_deref())?)?,
+            GistCommands::Edit { gist, files, filename, remove, description } => {
+                let options = commands::GistEditOptions {
+                    files: files.clone(),
+                    filename: filename.clone(),
+                    remove: remove.clone(),
+                    description: description.clone(),
+                };
+                output::emit(&commands::gist_edit(gist, &options)?)?
+            }
+            GistCommands::Delete { gist, yes } => output::emit(&commands::gist_delete(gist, *yes)?)?,
+        },
+        Commands::Net { command } => match command {
+            NetCommands::Dns { host } => output::emit(&commands::net_dns(host)?)?,
+            NetCommands::Port { host, port, timeout } => output::emit(&commands::net_port(host, *port, *timeout)?)?,
+            NetCommands::Http { url } => output::emit(&commands::net_http(url)?)?,
+            NetCommands::Doctor => {
+                let report = commands::net_doctor()?;
+                output::emit(&report)?;
+                report.ensure_passed()?
+            }
+        },
+        Commands::Auth { command } => match command {
+            AuthCommands::Login { with_token: true, .. } => output::emit(&auth::login_with_token()?)?,
+            AuthCommands::Login { no_browser, scopes, .. } => output::emit(&auth::login(!no_browser, scopes)?)?,
+        },
+        Commands::Cache { command } => match command {
+            CacheCommands::Stats => output::emit(&commands::cache_stats()?)?,
+            CacheCommands::Clear => output::emit(&commands::cache_clear()?)?,
+        },
+        Commands::SelfUpdate { check } => output::emit(&commands::self_update(*check)?)?,
+        Commands::Completions { shell } => completions::print_registration(*shell)?,
+        Commands::Man { dir: Some(dir) } => {
+            std::fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(Cli::command(), dir)?;
+            info!("{} Man pages written to {}", "✓".green(), dir.display());
+        }
+        Commands::Man { dir: None } => clap_mangen::Man::new(Cli::command()).render(&mut io::stdout())?,
     }
 
     Ok(())
 }
 
-fn init_logger() {
-    Builder::new()
-        .filter_level(LevelFilter::Info)
+fn init_colors(choice: ColorChoice) -> bool {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        // Human output goes to stderr, so that's the stream that matters.
+        ColorChoice::Auto => {
+            env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()) && io::stderr().is_terminal()
+        }
+    };
+    colored::control::set_override(enabled);
+    enabled
+}
+
+// Progress messages are noise for scripts, so JSON output only keeps
+// warnings and quiet mode only errors. -v raises the level from there, and
+// KIT_LOG (or RUST_LOG) replaces it entirely with env_logger filter syntax.
+fn init_logger(globals: &GlobalArgs, colors: bool) {
+    let base = match globals.output {
+        output::OutputFormat::Text | output::OutputFormat::Markdown => LevelFilter::Info,
+        output::OutputFormat::Json => LevelFilter::Warn,
+        output::OutputFormat::Quiet => LevelFilter::Error,
+    };
+    let level = if globals.quiet {
+        LevelFilter::Error
+    } else {
+        LevelFilter::iter()
+            .nth(base as usize + globals.verbose as usize)
+            .unwrap_or(LevelFilter::Trace)
+    };
+
+    let mut builder = Builder::new();
+    builder.filter_level(level);
+    builder.write_style(if colors { WriteStyle::Always } else { WriteStyle::Never });
+    if let Some(filters) = env::var("KIT_LOG").ok().or_else(|| env::var("RUST_LOG").ok()) {
+        builder.parse_filters(&filters);
+    }
+
+    builder
         .format(|buf, record| {
             let level_string = match record.level() {
                 log::Level::Error => "ERROR".red().bold(),
@@ -0,0 +1,58 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// How command results are reported. Human-readable messages always go to
+/// stderr through the logger; only `Json`, and commands whose result is
+/// prose or file contents, write anything to stdout.
+#[derive(Clone, Copy, PartialEq, Debug, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// Same as text, except that `ai-explain` prints its explanation as
+    /// Markdown rather than plain text.
+    Markdown,
+    Json,
+    Quiet,
+}
+
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+pub fn emit<T: Serialize>(result: &T) -> Result<()> {
+    if format() == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    }
+    Ok(())
+}
+
+/// A failure the emitted result already describes, such as a failed check
+/// in a report. It still makes kit exit non-zero, but no error document is
+/// printed after the result.
+#[derive(Debug)]
+pub struct Reported(pub String);
+
+impl fmt::Display for Reported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Reported {}
+
+pub fn emit_error(err: &anyhow::Error) {
+    if format() == OutputFormat::Json && !err.is::<Reported>() {
+        let error = serde_json::json!({ "error": format!("{:#}", err) });
+        println!("{}", error);
+    }
+}
@@ -0,0 +1,277 @@
+//! Offline HTTP harness for tests.
+//!
+//! A single local server is shared by every test in the process. Routes replay
+//! recorded responses from `tests/fixtures`; running the tests with
+//! `KIT_RECORD_FIXTURES=1` forwards requests for missing fixtures to the real
+//! service and writes the response to disk instead.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::{env, fs, thread};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{auth, cache};
+use crate::config::{self, Endpoints};
+
+const RECORD_ENV: &str = "KIT_RECORD_FIXTURES";
+
+#[derive(Serialize, Deserialize)]
+pub struct Fixture {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Replayed verbatim when a string, serialized as JSON otherwise.
+    pub body: serde_json::Value,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+struct Route {
+    method: String,
+    path: String,
+    /// Replayed in order; the last one repeats once the list is exhausted.
+    fixtures: Vec<String>,
+    hits: usize,
+    upstream: Option<String>,
+}
+
+pub struct MockServer {
+    pub base_url: String,
+    routes: Arc<Mutex<Vec<Route>>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    fn start() -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind mock server");
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = MockServer {
+            base_url,
+            routes: Arc::new(Mutex::new(Vec::new())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let routes = Arc::clone(&server.routes);
+        let requests = Arc::clone(&server.requests);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let routes = Arc::clone(&routes);
+                let requests = Arc::clone(&requests);
+                thread::spawn(move || {
+                    let _ = handle_connection(stream, &routes, &requests);
+                });
+            }
+        });
+
+        server
+    }
+
+    /// Serves `fixtures` (names relative to `tests/fixtures`, without the
+    /// `.json` extension) for `method path`. `upstream` is the real URL the
+    /// request is forwarded to when recording.
+    pub fn route(&self, method: &str, path: &str, fixtures: &[&str], upstream: Option<&str>) {
+        self.routes.lock().unwrap().push(Route {
+            method: method.to_string(),
+            path: path.to_string(),
+            fixtures: fixtures.iter().map(|f| f.to_string()).collect(),
+            hits: 0,
+            upstream: upstream.map(str::to_string),
+        });
+    }
+
+    pub fn requests_to(&self, path: &str) -> Vec<RecordedRequest> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.path == path)
+            .cloned()
+            .collect()
+    }
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(format!("{}.json", name))
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    routes: &Mutex<Vec<Route>>,
+    requests: &Mutex<Vec<RecordedRequest>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            headers.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let request = RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    };
+    requests.lock().unwrap().push(request.clone());
+
+    let fixture = resolve_fixture(&request, routes);
+    write_response(stream, &fixture)
+}
+
+fn resolve_fixture(request: &RecordedRequest, routes: &Mutex<Vec<Route>>) -> Fixture {
+    let (name, upstream) = {
+        let mut routes = routes.lock().unwrap();
+        let Some(route) = routes
+            .iter_mut()
+            .find(|r| r.method == request.method && r.path == request.path)
+        else {
+            return not_found(request);
+        };
+        let index = route.hits.min(route.fixtures.len() - 1);
+        route.hits += 1;
+        (route.fixtures[index].clone(), route.upstream.clone())
+    };
+
+    let path = fixture_path(&name);
+    if let Ok(contents) = fs::read_to_string(&path) {
+        return serde_json::from_str(&contents).expect("Invalid fixture file");
+    }
+
+    match upstream {
+        Some(upstream) if env::var_os(RECORD_ENV).is_some() => {
+            let fixture = record(request, &upstream).expect("Failed to record fixture");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, serde_json::to_string_pretty(&fixture).unwrap()).unwrap();
+            fixture
+        }
+        _ => panic!("Missing fixture {} (set {} to record it)", path.display(), RECORD_ENV),
+    }
+}
+
+fn record(request: &RecordedRequest, upstream: &str) -> reqwest::Result<Fixture> {
+    let client = reqwest::blocking::Client::new();
+    let mut builder = client.request(request.method.parse().unwrap(), upstream);
+    for (key, value) in &request.headers {
+        if !key.eq_ignore_ascii_case("host") && !key.eq_ignore_ascii_case("content-length") {
+            builder = builder.header(key, value);
+        }
+    }
+
+    let response = builder.body(request.body.clone()).send()?;
+    let status = response.status().as_u16();
+    let mut headers = BTreeMap::new();
+    if let Some(content_type) = response.headers().get("content-type") {
+        headers.insert("content-type".to_string(), content_type.to_str().unwrap_or_default().to_string());
+    }
+    let body = response.text()?;
+
+    Ok(Fixture {
+        status,
+        headers,
+        body: serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body)),
+    })
+}
+
+fn not_found(request: &RecordedRequest) -> Fixture {
+    Fixture {
+        status: 404,
+        headers: BTreeMap::new(),
+        body: serde_json::Value::String(format!("no route for {} {}", request.method, request.path)),
+    }
+}
+
+fn write_response(mut stream: TcpStream, fixture: &Fixture) -> std::io::Result<()> {
+    let body = match &fixture.body {
+        serde_json::Value::String(raw) => raw.clone(),
+        value => value.to_string(),
+    };
+
+    let mut response = format!("HTTP/1.1 {} MOCK\r\n", fixture.status);
+    for (key, value) in &fixture.headers {
+        response.push_str(&format!("{}: {}\r\n", key, value));
+    }
+    response.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    response.push_str(&body);
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+/// Starts the shared server, points every endpoint at it and seeds an
+/// isolated config file with test credentials.
+pub fn server() -> &'static MockServer {
+    static SERVER: OnceLock<MockServer> = OnceLock::new();
+
+    SERVER.get_or_init(|| {
+        let server = MockServer::start();
+
+        let config_dir = env::temp_dir().join(format!("kit-test-{}", std::process::id()));
+        fs::create_dir_all(&config_dir).unwrap();
+        let config_file = config_dir.join("config.json");
+        fs::write(
+            &config_file,
+            r#"{"github_token":"test-token","github_username":"octocat","openai_api_key":"test-key"}"#,
+        )
+        .unwrap();
+        config::set_config_path(config_file);
+        // A developer's own token would otherwise win over the test config
+        // and be sent to the mock server.
+        auth::set_token_env_lookup(|_| None);
+        cache::set_dir(config_dir.join("cache"));
+
+        config::set_endpoints(Endpoints {
+            github_api: format!("{}/api", server.base_url),
+            github_web: format!("{}/web", server.base_url),
+            openai_api: format!("{}/openai/v1", server.base_url),
+            ip_trace: format!("{}/cdn-cgi/trace", server.base_url),
+            ip_trace_v6: format!("{}/cdn-cgi/trace", server.base_url),
+        });
+
+        server
+    })
+}
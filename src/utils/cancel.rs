@@ -0,0 +1,52 @@
+use std::sync::Arc;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+
+static CANCELLED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+static ARMED: AtomicBool = AtomicBool::new(false);
+
+/// While a scope is alive, Ctrl-C sets its flag instead of killing the
+/// process, so in-flight work can stop and report what it already has.
+pub struct CancelScope {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelScope {
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.flag)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for CancelScope {
+    fn drop(&mut self) {
+        ARMED.store(false, Ordering::SeqCst);
+    }
+}
+
+pub fn scope() -> Result<CancelScope> {
+    let flag = match CANCELLED.get() {
+        Some(flag) => Arc::clone(flag),
+        None => {
+            let flag = Arc::new(AtomicBool::new(false));
+            let handler_flag = Arc::clone(&flag);
+            ctrlc::set_handler(move || {
+                if ARMED.load(Ordering::SeqCst) {
+                    handler_flag.store(true, Ordering::SeqCst);
+                } else {
+                    std::process::exit(130);
+                }
+            })?;
+            Arc::clone(CANCELLED.get_or_init(|| flag))
+        }
+    };
+
+    flag.store(false, Ordering::SeqCst);
+    ARMED.store(true, Ordering::SeqCst);
+    Ok(CancelScope { flag })
+}
@@ -1,8 +1,89 @@
+use std::path::Path;
+
 use anyhow::Result;
 use arboard::Clipboard;
+use ring::digest;
+
+pub mod cancel;
+pub mod progress;
 
 pub fn copy_to_clipboard(text: &str) -> Result<()> {
     let mut clipboard = Clipboard::new()?;
     clipboard.set_text(text)?;
     Ok(())
 }
+
+pub fn read_clipboard() -> Result<String> {
+    let mut clipboard = Clipboard::new()?;
+    Ok(clipboard.get_text()?)
+}
+
+/// The last component of `path`, for naming uploads after local files.
+pub fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    digest::digest(&digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1_000 => format!("{} B", bytes),
+        1_000..1_000_000 => format!("{:.1} KB", bytes as f64 / 1_000.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+    }
+}
+
+/// Shell-style matching of `text` against `pattern`, where `*` matches any
+/// run of characters and `?` a single one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was seen, and the text position it currently covers up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, covered)) => {
+                    p = star + 1;
+                    t = covered + 1;
+                    backtrack = Some((star, covered + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_handles_stars_and_question_marks() {
+        assert!(glob_match("*.tar.gz", "kit-linux-amd64.tar.gz"));
+        assert!(glob_match("kit-*-arm64", "kit-macos-arm64"));
+        assert!(glob_match("v?.*", "v1.2"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*.zip", "kit.tar.gz"));
+        assert!(!glob_match("kit-?", "kit-10"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Byte progress bars for uploads and downloads.
+
+use std::io::{self, IsTerminal, Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::output::{self, OutputFormat};
+use crate::utils;
+
+const WIDTH: usize = 24;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar on stderr, drawn only when a person is watching and
+/// cleared again when dropped.
+pub struct Progress {
+    label: String,
+    total: u64,
+    done: u64,
+    visible: bool,
+    last_drawn: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(label: &str, total: u64) -> Progress {
+        Progress {
+            label: label.to_string(),
+            total,
+            done: 0,
+            visible: io::stderr().is_terminal() && matches!(output::format(), OutputFormat::Text | OutputFormat::Markdown),
+            last_drawn: None,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+        if self.last_drawn.is_none_or(|drawn| drawn.elapsed() >= REDRAW_INTERVAL) {
+            self.draw();
+        }
+    }
+
+    fn draw(&mut self) {
+        if !self.visible {
+            return;
+        }
+        self.last_drawn = Some(Instant::now());
+
+        let fraction = if self.total == 0 { 1.0 } else { (self.done as f64 / self.total as f64).min(1.0) };
+        let filled = (fraction * WIDTH as f64) as usize;
+        let _ = write!(
+            io::stderr(),
+            "\r\x1b[2K{} [{}{}] {:>3}% {}/{}",
+            self.label,
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            (fraction * 100.0) as u32,
+            utils::format_size(self.done),
+            utils::format_size(self.total)
+        );
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.visible && self.last_drawn.is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[2K");
+        }
+    }
+}
+
+/// Reports everything read through it to a `Progress`.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: Progress,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, progress: Progress) -> ProgressReader<R> {
+        ProgressReader { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.advance(read as u64);
+        Ok(read)
+    }
+}